@@ -6,40 +6,104 @@
 /**
  * Verify that the conditions for a release are satisfied.
  *
- * The conditions for a release checked by this function are:
- *
- *    1. That the CARGO_REGISTRY_TOKEN environment variable is set and is
- *       non-empty.
- *    2. That it can construct the graph of all of the dependencies in the
- *       workspace.
- *    3. That the dependencies and build-dependencies of all of crates in the
- *       workspace are suitable for publishing to `crates.io`.
- *
- * If `manifest_path` is provided then it is expect to give the path to the
- * `Cargo.toml` file for the root of the workspace. If `manifest_path` is `None`
- * then `verify_conditions` will look for the root of the workspace in a
- * `Cargo.toml` file in the current directory. If one of the conditions for a
- * release are not satisfied then an explanation for that will be written to
- * `output`.
+ * The conditions are those checked by `verify_conditions_with_args` for the
+ * workspace and environment of `context`. The registry of `opts` defaults to the one
+ * in `context`.
  *
  * This implements the `verifyConditions` step for `semantic-release` for a
  * Cargo-based rust workspace.
  */
-export function verifyConditions(): void
+export function verifyConditions(opts?: VerifyArgs | undefined | null, context?: StepOptions | undefined | null): Promise<void>
+/** Arguments to be passed to the `verify_conditions_with_args` function. */
+export interface VerifyArgs {
+  /** The alternate registry to which the crates will be published. */
+  registry?: string
+  /**
+   * The template for the web url of the published crates, overriding the one in
+   * the workspace metadata.
+   */
+  urlTemplate?: string
+  /**
+   * The crate linked in the release record, overriding the one in the workspace
+   * metadata.
+   */
+  mainCrate?: string
+}
+/**
+ * The directory and environment in which a step runs, as given by the context of the
+ * step under `semantic-release`.
+ */
+export interface StepOptions {
+  /**
+   * The directory in which to run cargo and read the cargo configuration (defaults
+   * to the current directory of the process).
+   */
+  cwd?: string
+  /**
+   * The path to the `Cargo.toml` file of the root of the workspace, relative to
+   * `cwd` (defaults to the `Cargo.toml` file in `cwd`).
+   */
+  manifestPath?: string
+  /** The alternate registry to use when the arguments of the step do not name one. */
+  registry?: string
+  /**
+   * Environment variables added to, and overriding, the environment of the process
+   * when reading the registry tokens and running cargo.
+   */
+  env?: Record<string, string>
+}
+/** Arguments to be passed to the `prepare` function. */
+export interface PrepareArgs {
+  /** The crates of the workspace to prepare (defaults to all of them). */
+  packages?: Array<string>
+  /**
+   * Whether to add a section for the release to the changelog of each prepared
+   * crate.
+   */
+  changelog?: boolean
+  /** The notes for the changelog of each crate, keyed by the name of the crate. */
+  changelogNotes?: Record<string, string>
+  /**
+   * The git revision range, such as `v1.2.0..HEAD`, from which to generate the
+   * notes of the crates that are not in `changelogNotes`.
+   */
+  changelogRange?: string
+  /**
+   * The date in the heading of the new sections (defaults to today in UTC, as
+   * `YYYY-MM-DD`).
+   */
+  changelogDate?: string
+  /** Whether to leave the changelogs of crates without any notes untouched. */
+  skipUnchanged?: boolean
+  /** Whether to commit the files that were changed, and nothing else, to git. */
+  commit?: boolean
+  /**
+   * The template for the commit message, in which `{version}` is replaced by the
+   * new version (defaults to `chore(release): {version}`), overriding the one in
+   * the workspace metadata.
+   */
+  commitMessage?: string
+}
 /**
  * Prepare the Rust workspace for a release.
  *
- * Preparing the release updates the version of each crate in the workspace and of
- * the intra-workspace dependencies. The `version` field in the `packages` table of
- * each `Cargo.toml` file in the workspace is set to the supplied version. The
- * `version` field of each dependency or build-dependency that is otherwise
- * identified by a workspace-relative path dependencies is also set to the supplied
- * version (the version filed will be added if it isn't already present).
+ * This sets the version of the crates selected by `opts`, and of the dependencies on
+ * them, to `next_release_version` in the workspace of `context`, as described for
+ * the `prepare` function of the library.
  *
  * This implements the `prepare` step for `semantic-release` for a Cargo-based Rust
  * workspace.
  */
-export function prepare(nextReleaseVersion: string): void
+export function prepare(nextReleaseVersion: string, opts?: PrepareArgs | undefined | null, context?: StepOptions | undefined | null): Promise<void>
+/**
+ * Compute the changes that preparing the Rust workspace for a release would make.
+ *
+ * This runs the same updates as `prepare` against in-memory copies of the files in
+ * the workspace of `context` and resolves to a unified diff of the changes, without
+ * writing anything. The diff is empty if the workspace is already prepared for
+ * `nextReleaseVersion`.
+ */
+export function prepareDiff(nextReleaseVersion: string, opts?: PrepareArgs | undefined | null, context?: StepOptions | undefined | null): Promise<string>
 /** Arguments to be passed to the `publish` function. */
 export interface PublishArgs {
   /** Whether the `--no-dirty` flag should be passed to `cargo publish`. */
@@ -48,15 +112,547 @@ export interface PublishArgs {
   features?: Record<string, Array<string>>
   /** Optionally passes a `--registry` flag `cargo publish`. */
   registry?: string
+  /** The maximum number of crates to publish at the same time (defaults to 1). */
+  jobs?: number
+  /**
+   * The template for the web url of the published crates, overriding the one in
+   * the workspace metadata.
+   */
+  urlTemplate?: string
+  /**
+   * Whether to include docs.rs links for crates published to `crates.io`,
+   * overriding the workspace metadata.
+   */
+  docsRs?: boolean
+  /**
+   * The crate linked in the release record, overriding the one in the workspace
+   * metadata.
+   */
+  mainCrate?: string
+  /**
+   * Whether to resume an interrupted publish from its journal, skipping the crates
+   * that it already published.
+   */
+  resume?: boolean
+  /**
+   * The crates of the workspace to publish (defaults to all of the publishable
+   * crates).
+   */
+  packages?: Array<string>
+  /**
+   * Whether to create an annotated git tag for each published crate once every
+   * crate is published.
+   */
+  tag?: boolean
+  /**
+   * The format of the tags, such as `{name}-v{version}`, overriding the one in the
+   * workspace metadata.
+   */
+  tagFormat?: string
+  /**
+   * The longest time, in seconds, that `cargo publish` may take for each crate
+   * before it is killed (defaults to no limit).
+   */
+  timeout?: number
 }
 /**
  * Publish the publishable crates from the workspace.
  *
- * The publishable crates are the crates in the workspace other than those
- * whose `package.publish` field is set to `false` or that includes a registry other
- * than `crates.io`.
+ * This publishes the crates of the workspace of `context`, running `cargo publish` in
+ * its directory and environment, and resolves to the [`PublishReport`] that doubles
+ * as the release record for `semantic-release`. The registry of `opts` defaults to
+ * the one in `context`. If a crate fails to publish, the error that the promise
+ * rejects with has a `report` property with the outcome for each crate.
  *
  * This implements the `publish` step for `semantic-release` for a Cargo-based
  * Rust workspace.
  */
-export function publish(opts?: PublishArgs | undefined | null): void
+export function publish(opts?: PublishArgs | undefined | null, context?: StepOptions | undefined | null): Promise<PublishReport>
+/**
+ * The report of publishing the crates of a workspace.
+ *
+ * The `name` and `url` fields form the release record for `semantic-release` so the
+ * report can be returned as is from the `publish` step.
+ */
+export interface PublishReport {
+  /** The name of the release. */
+  name: string
+  /** The url of the main crate of the release, if there is one. */
+  url?: string
+  /** The docs.rs url of the main crate of the release, if docs.rs links are enabled. */
+  docsUrl?: string
+  /** The registry to which the crates were published. */
+  registry: string
+  /**
+   * The crates of the workspace in the order in which they were considered for
+   * publication.
+   */
+  crates: Array<CrateReport>
+}
+/** The outcome of publishing a single crate. */
+export interface CrateReport {
+  /** The name of the crate. */
+  name: string
+  /** The version of the crate. */
+  version: string
+  /** The registry to which the crate was (or would have been) published. */
+  registry: string
+  /** Whether the crate was published. */
+  status: PublishStatus
+  /** How long `cargo publish` ran for the crate, in milliseconds. */
+  durationMs: number
+  /** The last lines of the stderr output of `cargo publish` for the crate. */
+  stderr?: string
+  /** The url of the published crate on its registry, if it is known. */
+  url?: string
+  /** The docs.rs url of the published crate, if docs.rs links are enabled. */
+  docsUrl?: string
+}
+/** The publication status of a crate. */
+export const enum PublishStatus {
+  /** The crate was published. */
+  Published = 'published',
+  /**
+   * The crate was not published, either because it is not publishable to the
+   * target registry or because an earlier crate failed to publish.
+   */
+  Skipped = 'skipped',
+  /** `cargo publish` failed for the crate. */
+  Failed = 'failed'
+}
+/** Arguments to be passed to the `nextVersion` function. */
+export interface NextVersionArgs {
+  /** The prefix of the release tags, followed by the version (defaults to `v`). */
+  tagPrefix?: string
+}
+/**
+ * Find the next release version from the conventional commits since the last
+ * release.
+ *
+ * The last release is the highest version among the release tags reachable from
+ * `HEAD` of the git repository in the current directory. A breaking change calls for
+ * a major release (a minor release while the major version is 0), a `feat` for a
+ * minor release and a `fix` or `perf` for a patch release. Without a previous
+ * release the next version is 1.0.0. `null` is returned if no commit calls for a
+ * release.
+ *
+ * This gives the version that the `analyzeCommits` step of `semantic-release` would
+ * choose, for use without `semantic-release`.
+ */
+export function nextVersion(opts?: NextVersionArgs | undefined | null): string | null
+/** Arguments to be passed to the `generateNotes` function. */
+export interface GenerateNotesArgs {
+  /** The registry to which the crates are published (defaults to crates.io). */
+  registry?: string
+  /**
+   * The template for the web url of a published crate, such as
+   * `https://crates.internal/crates/{name}/{version}`. This overrides the
+   * template configured for the registry in the workspace metadata.
+   */
+  urlTemplate?: string
+  /** Whether to include docs.rs links for crates published to crates.io. */
+  docsRs?: boolean
+}
+/**
+ * Generate release notes for the commits since `lastRelease`, with a section for
+ * each crate of the workspace.
+ *
+ * The commits are those from the `gitHead` of `lastRelease` to the `gitHead` of
+ * `nextRelease` (`HEAD` if it has none), or every commit up to the `gitHead` of
+ * `nextRelease` for the first release. A commit belongs to the crates whose
+ * directories contain the files it touched and to the crate named by its
+ * conventional commit scope. Each crate with a breaking change, feature, bug fix or
+ * performance improvement gets a Markdown section headed by its name and the
+ * version of `nextRelease`, linked to the published crate when its url is known.
+ * The registry of `opts` defaults to the one in `context`.
+ *
+ * This implements the `generateNotes` step for `semantic-release` for a Cargo-based
+ * Rust workspace.
+ */
+export function generateNotes(lastRelease: LastRelease | undefined | null, nextRelease: NextRelease, opts?: GenerateNotesArgs | undefined | null, context?: StepOptions | undefined | null): Promise<string>
+/** The crates of a workspace affected by the changes since a git ref. */
+export interface AffectedPackages {
+  /** The git ref that the workspace was compared with. */
+  base: string
+  /** The crates with changed files, in dependency order. */
+  changed: Array<string>
+  /**
+   * The crates with changed files and the crates that depend on them, in
+   * dependency order.
+   */
+  affected: Array<string>
+}
+/**
+ * Find the crates of the workspace affected by the changes since the git ref `base`.
+ *
+ * A crate is affected if a file in its directory changed since `base` (including
+ * uncommitted changes), or if it depends on an affected crate. The names of the
+ * affected crates can be used as the `packages` of `PrepareArgs` and `PublishArgs`
+ * to release only those crates.
+ *
+ * The workspace is the one of `context`.
+ */
+export function affected(base: string, context?: StepOptions | undefined | null): Promise<AffectedPackages>
+/** Arguments to be passed to the `tag` function. */
+export interface TagArgs {
+  /** The registry to which the crates are published (defaults to crates.io). */
+  registry?: string
+  /**
+   * The format of the tags, such as `{name}-v{version}`, overriding the one in the
+   * workspace metadata.
+   */
+  tagFormat?: string
+  /**
+   * The crates of the workspace to tag (defaults to all of the publishable
+   * crates).
+   */
+  packages?: Array<string>
+}
+/**
+ * Create an annotated git tag for each publishable crate of the workspace.
+ *
+ * Each tag points at `HEAD` of the workspace of `context` and is named by the tag
+ * format (by default `{name}-v{version}`) with the name and current version of the
+ * crate. Tags that already point at `HEAD` are kept, and nothing is tagged if any tag
+ * already points at another commit. The registry of `opts` defaults to the one in
+ * `context`.
+ */
+export function tag(opts?: TagArgs | undefined | null, context?: StepOptions | undefined | null): Promise<TagReport>
+/** The report of tagging the released crates of a workspace. */
+export interface TagReport {
+  /** The commit that the tags point at. */
+  commit: string
+  /** The tags of the crates in the order in which they are published. */
+  tags: Array<CrateTag>
+}
+/** The tag of a single crate. */
+export interface CrateTag {
+  /** The name of the crate. */
+  name: string
+  /** The version of the crate. */
+  version: string
+  /** The name of the tag. */
+  tag: string
+  /** Whether the tag was created. */
+  status: TagStatus
+}
+/** The status of the tag of a crate. */
+export const enum TagStatus {
+  /** The tag was created. */
+  Created = 'created',
+  /** The tag already pointed at the commit. */
+  Existing = 'existing'
+}
+/** Arguments to be passed to the `yank` function. */
+export interface YankArgs {
+  /** Optionally passes a `--registry` flag to `cargo yank`. */
+  registry?: string
+  /** Whether to un-yank the version instead of yanking it. */
+  undo?: boolean
+}
+/**
+ * Yank a version of the publishable crates of the workspace.
+ *
+ * This rolls back a release that went wrong after some of its crates were
+ * published, running `cargo yank` in the directory and environment of `context`. The
+ * publishable crates are yanked in the reverse of the order in which they are
+ * published, and crates that were never published at `version` are reported as
+ * such. With the `undo` option the version is un-yanked instead. The registry of
+ * `opts` defaults to the one in `context`. If a crate fails to be yanked, the error
+ * that the promise rejects with has a `report` property with the outcome for each
+ * crate.
+ */
+export function yank(version: string, opts?: YankArgs | undefined | null, context?: StepOptions | undefined | null): Promise<YankReport>
+/** The report of yanking (or un-yanking) a version of the crates of a workspace. */
+export interface YankReport {
+  /** The version that was yanked. */
+  version: string
+  /** The registry from which the version was yanked. */
+  registry: string
+  /** Whether the version was un-yanked instead of yanked. */
+  undo: boolean
+  /** The publishable crates of the workspace in the order in which they were yanked. */
+  crates: Array<YankedCrate>
+}
+/** The outcome of yanking a version of a single crate. */
+export interface YankedCrate {
+  /** The name of the crate. */
+  name: string
+  /** Whether the version of the crate was yanked. */
+  status: YankStatus
+  /** The last lines of the stderr output of `cargo yank` for the crate. */
+  stderr?: string
+}
+/** The yank status of a crate. */
+export const enum YankStatus {
+  /** The version of the crate was yanked. */
+  Yanked = 'yanked',
+  /** The version of the crate was un-yanked. */
+  Unyanked = 'unyanked',
+  /** The crate was never published at the version. */
+  Unpublished = 'unpublished',
+  /** `cargo yank` failed for the crate. */
+  Failed = 'failed'
+}
+/**
+ * Plan a release of the workspace without changing anything.
+ *
+ * The plan records the edits to the manifests, lockfiles and changelogs that
+ * `prepare` would make to the workspace of `context` for `nextReleaseVersion` with
+ * `prepareOpts`, and the `cargo publish` commands and tags that `publish` would then
+ * run and create with `opts`, along with a fingerprint of the workspace. It can be
+ * serialized to JSON, reviewed, and later run with `apply`. The `commit` and `resume`
+ * options are not used, and the registry of `opts` defaults to the one in `context`.
+ */
+export function plan(nextReleaseVersion: string, prepareOpts?: PrepareArgs | undefined | null, opts?: PublishArgs | undefined | null, context?: StepOptions | undefined | null): Promise<ReleasePlan>
+/**
+ * Apply a release plan made by `plan`.
+ *
+ * Applying the plan writes its edits to the workspace of `context` and then runs its
+ * `cargo publish` commands level by level, as `publish` does, and creates its tags
+ * once every crate is published. Nothing is done if the manifests, lockfiles,
+ * changelogs or cargo configuration of the workspace have changed since the plan was
+ * made. If a crate fails to publish, the error that the promise rejects with has a
+ * `report` property with the outcome for each crate.
+ */
+export function apply(plan: ReleasePlan, context?: StepOptions | undefined | null): Promise<PublishReport>
+/**
+ * A plan for releasing the crates of a workspace.
+ *
+ * The plan records the crates of the workspace, the order in which they will be
+ * published, and the exact edits and `cargo publish` commands of the release, so
+ * that it can be reviewed before it is applied.
+ */
+export interface ReleasePlan {
+  /** The version to which the crates of the workspace will be set. */
+  nextVersion: string
+  /** The registry to which the crates will be published. */
+  registry: string
+  /**
+   * A SHA-256 fingerprint of the files of the workspace that the plan depends on
+   * when the plan was made: the manifests and lockfiles, the changelogs and the
+   * cargo configuration.
+   */
+  fingerprint: string
+  /** The crates of the workspace in dependency order. */
+  crates: Array<PlannedCrate>
+  /**
+   * The names of the crates to publish, grouped into levels that only depend on
+   * crates in earlier levels.
+   */
+  publishOrder: Array<Array<string>>
+  /** The edits to the manifests and lockfiles of the workspace. */
+  edits: Array<PlannedEdit>
+  /** A unified diff of the edits, for review. */
+  diff: string
+  /** The `cargo publish` commands, in the order in which they will be run. */
+  commands: Array<PlannedPublish>
+  /** The name of the main crate of the release, if there is one. */
+  mainCrate?: string
+  /**
+   * The longest time, in seconds, that each `cargo publish` command may take, if
+   * it is limited.
+   */
+  timeout?: number
+  /**
+   * The maximum number of `cargo publish` commands of a level to run at the same
+   * time (defaults to 1).
+   */
+  jobs?: number
+  /**
+   * The format of the annotated git tags to create for the published crates once
+   * every crate is published, if they are tagged.
+   */
+  tagFormat?: string
+}
+/** A crate of the workspace in a `ReleasePlan`. */
+export interface PlannedCrate {
+  /** The name of the crate. */
+  name: string
+  /** The current version of the crate. */
+  version: string
+  /** The version of the crate after the release. */
+  nextVersion: string
+  /** Whether the crate will be published. */
+  publish: boolean
+}
+/** The new contents of a file in a `ReleasePlan`. */
+export interface PlannedEdit {
+  /** The path of the file relative to the root of the workspace. */
+  path: string
+  /** Whether the file is a manifest, a lockfile or a changelog. */
+  kind: EditKind
+  /** The new contents of the file. */
+  contents: string
+}
+/** The kind of file edited by a `PlannedEdit`. */
+export const enum EditKind {
+  /** A `Cargo.toml` file. */
+  Manifest = 'manifest',
+  /** A `Cargo.lock` file. */
+  Lockfile = 'lockfile',
+  /** The changelog of a crate. */
+  Changelog = 'changelog'
+}
+/** A `cargo publish` command in a `ReleasePlan`. */
+export interface PlannedPublish {
+  /** The name of the crate to publish. */
+  name: string
+  /** The version of the crate to publish. */
+  version: string
+  /** The manifest of the crate relative to the root of the workspace. */
+  manifestPath: string
+  /** The arguments to `cargo`, which is run from the root of the workspace. */
+  args: Array<string>
+  /** The url of the published crate on its registry, if it is known. */
+  url?: string
+  /** The docs.rs url of the published crate, if docs.rs links are enabled. */
+  docsUrl?: string
+}
+/** Arguments to be passed to the `register_logger` function. */
+export interface LoggerOptions {
+  /**
+   * The most verbose level that is passed to the callback, one of `error`, `warn`,
+   * `info`, `debug` or `trace` (defaults to `info`).
+   */
+  level?: string
+}
+/**
+ * Pass the log records of the plugin to a JavaScript callback.
+ *
+ * The callback is called with the name of the `context.logger` method for the record,
+ * `error`, `warn` or `log`, and the message. Records at the `info`, `debug` and
+ * `trace` levels go to `log`. The callback does not keep the Node process alive.
+ */
+export function registerLogger(callback: (method: 'log' | 'warn' | 'error', message: string) => void, opts?: LoggerOptions | undefined | null): void
+/** Arguments to be passed to the `verify_release` function. */
+export interface VerifyReleaseArgs {
+  /** The alternate registry to which the crates will be published. */
+  registry?: string
+  /** The crates of the workspace to release (defaults to all of them). */
+  packages?: Array<string>
+  /**
+   * Whether to check with the registry that the new version of each crate has not
+   * been published already (defaults to true).
+   */
+  checkRegistry?: boolean
+}
+/** The release that `semantic-release` is making, from `context.nextRelease`. */
+export interface NextRelease {
+  /** The version of the release. */
+  version: string
+  /** The git tag of the release. */
+  gitTag?: string
+  /** The git commit of the release. */
+  gitHead?: string
+  /** The release channel of the release. */
+  channel?: string
+  /** The release notes of the release. */
+  notes?: string
+}
+/** The last release made by `semantic-release`, from `context.lastRelease`. */
+export interface LastRelease {
+  /** The version of the release. */
+  version?: string
+  /** The git tag of the release. */
+  gitTag?: string
+  /** The git commit of the release. */
+  gitHead?: string
+}
+/** The fields of the `semantic-release` context of the `success` step. */
+export interface SuccessContext {
+  /** The release that was made, from `context.nextRelease`. */
+  nextRelease?: NextRelease
+  /** The release records that `publish` returned, from `context.releases`. */
+  releases: Array<PublishReport>
+}
+/**
+ * Verify that the version of `next_release` can be released from the workspace.
+ *
+ * The version must be a valid semantic version that is not earlier than the version
+ * of any of the crates to release and, unless `opts` turns off the registry check,
+ * must not be published already. The registry of `opts` defaults to the one in
+ * `context`.
+ *
+ * This implements the `verifyRelease` step for `semantic-release` for a Cargo-based
+ * Rust workspace.
+ */
+export function verifyRelease(nextRelease: NextRelease, opts?: VerifyReleaseArgs | undefined | null, context?: StepOptions | undefined | null): Promise<void>
+/**
+ * Summarize the crates published by the release.
+ *
+ * The summary of each release record in `context` is logged and the summaries are
+ * returned.
+ *
+ * This implements the `success` step for `semantic-release` for a Cargo-based Rust
+ * workspace.
+ */
+export function success(context: SuccessContext): string
+/**
+ * Report what a failed release left published.
+ *
+ * The crates recorded in the publish journal of the workspace of `context` are logged
+ * with a hint to resume the publish or to yank them, and the report is written as
+ * `rollback.json` next to the journal.
+ *
+ * This implements the `fail` step for `semantic-release` for a Cargo-based Rust
+ * workspace.
+ */
+export function fail(context?: StepOptions | undefined | null): Promise<FailReport>
+/** The report of a release that failed. */
+export interface FailReport {
+  /** The path of the journal of the interrupted publish, if there is one. */
+  journal?: string
+  /**
+   * The path of the file to which this report, with its hint to finish or roll back
+   * the release, was written.
+   */
+  rollback: string
+  /** The registry to which the crates were being published, if it is known. */
+  registry?: string
+  /** The crates that were published before the release failed. */
+  published: Array<PublishedCrate>
+  /** How to finish or roll back the release. */
+  hint: string
+}
+/** A crate published by a release that failed. */
+export interface PublishedCrate {
+  /** The name of the crate. */
+  name: string
+  /** The version of the crate. */
+  version: string
+}
+/** Arguments to be passed to the `list_packages` function. */
+export interface ListPackagesArgs {
+  /** The alternate registry to which the crates would be published. */
+  registry?: string
+}
+/**
+ * List the crates of the workspace in the order in which they are published.
+ *
+ * Each crate is listed with its version, its manifest, whether it would be published
+ * to the registry of `opts` (and if not, why not) and the workspace crates that it
+ * depends on.
+ *
+ * The workspace is the one of `context`, whose directory and environment `cargo
+ * metadata` runs in. The registry of `opts` defaults to the one in `context`.
+ */
+export function listPackages(opts?: ListPackagesArgs | undefined | null, context?: StepOptions | undefined | null): Promise<Array<PackageInfo>>
+/** A crate of the workspace as it would be considered for a release. */
+export interface PackageInfo {
+  /** The name of the crate. */
+  name: string
+  /** The current version of the crate. */
+  version: string
+  /** The path to the `Cargo.toml` file of the crate. */
+  manifestPath: string
+  /** Whether the crate would be published to the target registry. */
+  publishable: boolean
+  /** Why the crate would not be published, if it would not. */
+  reason?: string
+  /**
+   * The workspace crates that the crate depends on, other than through
+   * `dev-dependencies`.
+   */
+  dependencies: Array<string>
+}
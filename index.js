@@ -17,20 +17,79 @@ const ARCH = arch();
 const ABI = getAbi(PLATFORM);
 const semanticReleaseCargo = require(`./napi/${MODULE_NAME}.${PLATFORM}-${ARCH}${ABI}.node`);
 
-function verifyConditions(pluginConfig, context) {
-  semanticReleaseCargo.verifyConditions();
+function stepContext(pluginConfig, context) {
+  return {
+    cwd: context.cwd,
+    manifestPath: pluginConfig.manifestPath,
+    registry: pluginConfig.registry,
+    env: context.env,
+  };
 }
 
-function prepare(pluginConfig, context) {
-  semanticReleaseCargo.prepare(context.nextRelease.version);
+function registerLogger(pluginConfig, context) {
+  semanticReleaseCargo.registerLogger(
+    (method, message) => context.logger[method](message),
+    { level: pluginConfig.logLevel },
+  );
 }
 
-function publish(pluginConfig, context) {
-  semanticReleaseCargo.publish(pluginConfig);
+async function verifyConditions(pluginConfig, context) {
+  registerLogger(pluginConfig, context);
+  await semanticReleaseCargo.verifyConditions(pluginConfig, stepContext(pluginConfig, context));
+}
+
+async function prepare(pluginConfig, context) {
+  registerLogger(pluginConfig, context);
+  await semanticReleaseCargo.prepare(
+    context.nextRelease.version,
+    pluginConfig,
+    stepContext(pluginConfig, context),
+  );
+}
+
+async function publish(pluginConfig, context) {
+  registerLogger(pluginConfig, context);
+  return semanticReleaseCargo.publish(pluginConfig, stepContext(pluginConfig, context));
+}
+
+async function verifyRelease(pluginConfig, context) {
+  registerLogger(pluginConfig, context);
+  await semanticReleaseCargo.verifyRelease(
+    context.nextRelease,
+    pluginConfig,
+    stepContext(pluginConfig, context),
+  );
+}
+
+async function generateNotes(pluginConfig, context) {
+  registerLogger(pluginConfig, context);
+  return semanticReleaseCargo.generateNotes(
+    context.lastRelease,
+    context.nextRelease,
+    pluginConfig,
+    stepContext(pluginConfig, context),
+  );
+}
+
+async function success(pluginConfig, context) {
+  registerLogger(pluginConfig, context);
+  semanticReleaseCargo.success({
+    nextRelease: context.nextRelease,
+    releases: (context.releases || []).filter((release) => Array.isArray(release.crates)),
+  });
+}
+
+async function fail(pluginConfig, context) {
+  registerLogger(pluginConfig, context);
+  await semanticReleaseCargo.fail(stepContext(pluginConfig, context));
 }
 
 module.exports = {
   verifyConditions,
+  verifyRelease,
+  generateNotes,
   prepare,
   publish,
+  success,
+  fail,
 };
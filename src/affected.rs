@@ -0,0 +1,136 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Detection of the crates of a workspace affected by the changes since a git ref.
+
+use std::{collections::HashSet, path::Path};
+
+use guppy::graph::{DependencyDirection, PackageGraph};
+use log::{debug, trace};
+use serde::Serialize;
+
+use crate::{commits::git, Result};
+
+#[cfg(feature = "napi-rs")]
+use napi_derive::napi;
+
+/// The crates of a workspace affected by the changes since a git ref.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedPackages {
+    /// The git ref that the workspace was compared with.
+    pub base: String,
+
+    /// The crates with changed files, in dependency order.
+    pub changed: Vec<String>,
+
+    /// The crates with changed files and the crates that depend on them, in
+    /// dependency order.
+    pub affected: Vec<String>,
+}
+
+/// Find the crates of the workspace of `graph` affected by the changes since `base`.
+///
+/// A changed file belongs to the member with the deepest directory that contains it.
+/// Dev-dependencies do not make a crate affected since they are not part of a
+/// published crate.
+pub(crate) fn affected_packages(graph: &PackageGraph, base: &str) -> Result<AffectedPackages> {
+    let workspace = graph.workspace();
+    let root = workspace.root().as_std_path();
+
+    let diff = git(
+        root,
+        &[
+            "diff",
+            "--name-only",
+            "--relative",
+            "--no-renames",
+            base,
+            "--",
+        ],
+    )?;
+
+    let members: Vec<_> = workspace
+        .iter_by_path()
+        .map(|(dir, pkg)| (dir.as_std_path(), pkg.name()))
+        .collect();
+    let changed: HashSet<_> = diff
+        .lines()
+        .filter_map(|path| {
+            let member = member_for_path(&members, Path::new(path));
+            trace!("{} belongs to {:?}", path, member);
+            member
+        })
+        .collect();
+    debug!("crates with changes since {}: {:?}", base, changed);
+
+    let mut affected = changed.clone();
+    let mut pending: Vec<_> = changed
+        .iter()
+        .filter_map(|name| workspace.member_by_name(name).ok())
+        .collect();
+    while let Some(pkg) = pending.pop() {
+        for link in pkg.reverse_direct_links() {
+            let from = link.from();
+            if !link.dev_only() && from.in_workspace() && affected.insert(from.name()) {
+                pending.push(from);
+            }
+        }
+    }
+
+    let in_order = |names: &HashSet<&str>| {
+        graph
+            .query_workspace()
+            .resolve_with_fn(|_, link| !link.dev_only())
+            .packages(DependencyDirection::Reverse)
+            .filter(|pkg| pkg.in_workspace() && names.contains(pkg.name()))
+            .map(|pkg| pkg.name().to_owned())
+            .collect()
+    };
+
+    Ok(AffectedPackages {
+        base: base.to_owned(),
+        changed: in_order(&changed),
+        affected: in_order(&affected),
+    })
+}
+
+/// The name of the member whose directory is the deepest one that contains `path`.
+///
+/// `members` are the directories of the members relative to the root of the
+/// workspace (which is the empty path) and their names.
+pub(crate) fn member_for_path<'a>(members: &[(&Path, &'a str)], path: &Path) -> Option<&'a str> {
+    members
+        .iter()
+        .filter(|(dir, _)| path.starts_with(dir))
+        .max_by_key(|(dir, _)| dir.components().count())
+        .map(|(_, name)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_belongs_to_deepest_member() {
+        let members = [
+            (Path::new(""), "root"),
+            (Path::new("crates/dep1"), "dep1"),
+            (Path::new("crates/dep1/nested"), "nested"),
+        ];
+
+        let member = |path| member_for_path(&members, Path::new(path));
+
+        assert_eq!(member("src/lib.rs"), Some("root"));
+        assert_eq!(member("crates/dep1/src/lib.rs"), Some("dep1"));
+        assert_eq!(member("crates/dep1/nested/Cargo.toml"), Some("nested"));
+        assert_eq!(member("crates/dep10/src/lib.rs"), Some("root"));
+        assert_eq!(member_for_path(&members[1..], Path::new("README.md")), None);
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Maintenance of the `CHANGELOG.md` file of each crate.
+//!
+//! A release adds a section headed by its version and date above the sections of
+//! earlier releases. Everything else in the changelog is kept as it is.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The path of the changelog of a crate relative to its directory, unless the crate
+/// configures another one.
+pub(crate) const DEFAULT_CHANGELOG: &str = "CHANGELOG.md";
+
+/// The title of a newly created changelog.
+const TITLE: &str = "# Changelog\n";
+
+/// The body of a section for a release without any notes.
+const NO_NOTES: &str = "No notable changes.";
+
+/// Add a section for `version` released on `date`, containing `notes`, to
+/// `changelog` (or to a new changelog if `changelog` is `None`).
+///
+/// The section goes before the first second-level heading, which is the section of
+/// the last release, or at the end if there are no sections yet. `None` is returned
+/// if the changelog already has a section for `version`.
+pub(crate) fn add_section(
+    changelog: Option<&str>,
+    version: &str,
+    date: &str,
+    notes: &str,
+) -> Option<String> {
+    let notes = notes.trim();
+    let section = format!(
+        "## {} ({})\n\n{}\n",
+        version,
+        date,
+        if notes.is_empty() { NO_NOTES } else { notes }
+    );
+
+    let Some(changelog) = changelog else {
+        return Some(format!("{}\n{}", TITLE, section));
+    };
+    if has_section(changelog, version) {
+        return None;
+    }
+
+    let mut offset = 0;
+    for line in changelog.split_inclusive('\n') {
+        if line.starts_with("## ") {
+            return Some(format!(
+                "{}{}\n{}",
+                &changelog[..offset],
+                section,
+                &changelog[offset..]
+            ));
+        }
+        offset += line.len();
+    }
+
+    let existing = changelog.trim_end();
+    if existing.is_empty() {
+        Some(format!("{}\n{}", TITLE, section))
+    } else {
+        Some(format!("{}\n\n{}", existing, section))
+    }
+}
+
+/// Whether `changelog` has a section for `version`, headed either by the version or
+/// by a link whose text is the version.
+fn has_section(changelog: &str, version: &str) -> bool {
+    changelog.lines().any(|line| {
+        let Some(heading) = line.strip_prefix("## ") else {
+            return false;
+        };
+        let heading = heading.strip_prefix('[').unwrap_or(heading);
+        heading
+            .strip_prefix(version)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', ']']))
+    })
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+pub(crate) fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86_400)
+        .unwrap_or_default();
+
+    civil_date(days as i64)
+}
+
+/// The date, as `YYYY-MM-DD`, that is `days` days after 1970-01-01.
+///
+/// This is the `civil_from_days` algorithm of Howard Hinnant's "chrono-Compatible
+/// Low-Level Date Algorithms".
+fn civil_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_goes_before_the_last_release() {
+        let changelog = "# Changes\n\nSome preamble.\n\n## 1.0.0 (2020-01-01)\n\n* first\n";
+
+        let updated = add_section(Some(changelog), "2.0.0", "2020-02-02", "* second\n");
+
+        assert_eq!(
+            updated.as_deref(),
+            Some(
+                "# Changes\n\nSome preamble.\n\n## 2.0.0 (2020-02-02)\n\n* second\n\n## 1.0.0 (2020-01-01)\n\n* first\n"
+            )
+        );
+    }
+
+    #[test]
+    fn section_of_missing_changelog_has_title() {
+        let created = add_section(None, "1.0.0", "2020-01-01", "");
+
+        assert_eq!(
+            created.as_deref(),
+            Some("# Changelog\n\n## 1.0.0 (2020-01-01)\n\nNo notable changes.\n")
+        );
+    }
+
+    #[test]
+    fn section_is_added_once() {
+        let changelog = "# Changelog\n\n## [2.0.0](https://example.com) (2020-02-02)\n";
+
+        assert_eq!(
+            add_section(Some(changelog), "2.0.0", "2020-02-02", ""),
+            None
+        );
+        assert!(add_section(Some(changelog), "2.0.0-rc.1", "2020-02-02", "").is_some());
+    }
+
+    #[test]
+    fn civil_date_of_days_since_epoch() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(20_745), "2026-10-19");
+    }
+}
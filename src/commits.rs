@@ -0,0 +1,371 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Analysis of conventional commits to find the next release version.
+//!
+//! This follows the default rules of the `analyzeCommits` step of `semantic-release`:
+//! a breaking change (a `!` after the type or scope, or a `BREAKING CHANGE:` footer)
+//! is a major release, a `feat` is a minor release, and a `fix` or `perf` is a patch
+//! release. While the major version is 0 a breaking change is a minor release.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use log::{debug, trace};
+use semver::Version;
+
+use crate::{Error, Result};
+
+/// The kind of release that a set of commits calls for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ReleaseType {
+    Patch,
+    Minor,
+    Major,
+}
+
+/// The version of the first release of a repository.
+const FIRST_RELEASE: Version = Version::new(1, 0, 0);
+
+/// Find the next release version of the git repository at `repository` from the
+/// commits since its last release tag.
+///
+/// Release tags are `tag_prefix` followed by a version. `None` is returned if none of
+/// the commits since the last release calls for a release.
+pub(crate) fn next_version(repository: &Path, tag_prefix: &str) -> Result<Option<Version>> {
+    let last_release = last_release(repository, tag_prefix)?;
+
+    let range = match &last_release {
+        Some((tag, version)) => {
+            debug!("last release is {} (tag {})", version, tag);
+            format!("{}..HEAD", tag)
+        }
+        None => {
+            debug!("no previous release tag with prefix {:?}", tag_prefix);
+            "HEAD".to_owned()
+        }
+    };
+    let log = git(repository, &["log", "--format=%B%x1e", &range])?;
+
+    let release_type = log
+        .split('\x1e')
+        .map(str::trim)
+        .filter(|message| !message.is_empty())
+        .filter_map(|message| {
+            let release_type = release_type(message);
+            trace!(
+                "{:?} for commit: {}",
+                release_type,
+                message.lines().next().unwrap_or_default()
+            );
+            release_type
+        })
+        .max();
+
+    Ok(release_type.map(|release_type| match last_release {
+        Some((_, version)) => bump(&version, release_type),
+        None => FIRST_RELEASE,
+    }))
+}
+
+/// The last release of the repository, as its tag and version.
+///
+/// This is the highest version, excluding pre-releases, among the tags that are
+/// reachable from `HEAD`.
+fn last_release(repository: &Path, tag_prefix: &str) -> Result<Option<(String, Version)>> {
+    let tags = git(repository, &["tag", "--merged", "HEAD"])?;
+
+    Ok(tags
+        .lines()
+        .filter_map(|tag| {
+            let version = Version::parse(tag.strip_prefix(tag_prefix)?).ok()?;
+            version.pre.is_empty().then(|| (tag.to_owned(), version))
+        })
+        .max_by(|(_, a), (_, b)| a.cmp(b)))
+}
+
+/// The header of a conventional commit message and whether it is a breaking change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ConventionalCommit<'a> {
+    /// The type of the commit, such as `feat` or `fix`.
+    pub(crate) typ: &'a str,
+
+    /// The scope of the commit, if it has one.
+    pub(crate) scope: Option<&'a str>,
+
+    /// The description of the change after the type and scope.
+    pub(crate) subject: &'a str,
+
+    /// Whether the commit is a breaking change.
+    pub(crate) breaking: bool,
+}
+
+impl<'a> ConventionalCommit<'a> {
+    /// Parse a commit message, returning `None` if it is not a conventional commit.
+    pub(crate) fn parse(message: &'a str) -> Option<Self> {
+        let mut lines = message.lines();
+        let header = lines.next()?.trim();
+        let (prefix, subject) = header.split_once(':')?;
+
+        let breaking_header = prefix.ends_with('!');
+        let prefix = prefix.trim_end_matches('!');
+        let (typ, scope) = match prefix.split_once('(') {
+            Some((typ, scope)) => (typ, Some(scope.strip_suffix(')')?)),
+            None => (prefix, None),
+        };
+        if typ.is_empty() || !typ.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        let breaking_footer = lines.any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+
+        Some(Self {
+            typ,
+            scope,
+            subject: subject.trim(),
+            breaking: breaking_header || breaking_footer,
+        })
+    }
+
+    /// The kind of release that the commit calls for, if any.
+    pub(crate) fn release_type(&self) -> Option<ReleaseType> {
+        if self.breaking {
+            return Some(ReleaseType::Major);
+        }
+
+        match self.typ.to_ascii_lowercase().as_str() {
+            "feat" => Some(ReleaseType::Minor),
+            "fix" | "perf" => Some(ReleaseType::Patch),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of release that a single commit message calls for, if any.
+pub(crate) fn release_type(message: &str) -> Option<ReleaseType> {
+    ConventionalCommit::parse(message)?.release_type()
+}
+
+/// Bump `version` for a release of type `release_type`.
+///
+/// While the major version is 0 a major release bumps the minor version.
+pub(crate) fn bump(version: &Version, release_type: ReleaseType) -> Version {
+    match release_type {
+        ReleaseType::Major if version.major == 0 => Version::new(0, version.minor + 1, 0),
+        ReleaseType::Major => Version::new(version.major + 1, 0, 0),
+        ReleaseType::Minor => Version::new(version.major, version.minor + 1, 0),
+        ReleaseType::Patch => Version::new(version.major, version.minor, version.patch + 1),
+    }
+}
+
+/// Commit the files at `paths`, and nothing else, to the git repository at
+/// `repository` with `message`, returning the new commit.
+pub(crate) fn commit_paths(repository: &Path, paths: &[PathBuf], message: &str) -> Result<String> {
+    let paths: Vec<_> = paths.iter().map(|path| path.to_string_lossy()).collect();
+    let paths: Vec<&str> = paths.iter().map(AsRef::as_ref).collect();
+
+    git(repository, &[&["add", "--"], &paths[..]].concat())?;
+    git(
+        repository,
+        &[
+            &["commit", "--quiet", "--message", message, "--"],
+            &paths[..],
+        ]
+        .concat(),
+    )?;
+    let commit = git(repository, &["rev-parse", "HEAD"])?;
+
+    Ok(commit.trim().to_owned())
+}
+
+/// Run git in `repository` and return its stdout.
+pub(crate) fn git(repository: &Path, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(repository).args(args);
+    trace!("running: {:?}", command);
+
+    let output = command
+        .output()
+        .map_err(|err| Error::git_error(err, args))?;
+    if !output.status.success() {
+        return Err(Error::git_status(output.status, args, &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    /// A git repository built for a test.
+    struct FixtureRepo {
+        dir: TempDir,
+    }
+
+    impl FixtureRepo {
+        fn new() -> Self {
+            let repo = Self {
+                dir: tempdir().expect("Couldn't create temp dir"),
+            };
+            repo.git(&["init", "--quiet"]);
+            repo
+        }
+
+        fn commit(&self, message: &str) -> &Self {
+            self.git(&["commit", "--quiet", "--allow-empty", "-m", message]);
+            self
+        }
+
+        fn tag(&self, tag: &str) -> &Self {
+            self.git(&["tag", tag]);
+            self
+        }
+
+        fn next_version(&self) -> Option<String> {
+            next_version(self.dir.path(), "v")
+                .expect("unable to find next version")
+                .map(|version| version.to_string())
+        }
+
+        fn git(&self, args: &[&str]) {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(self.dir.path())
+                .args([
+                    "-c",
+                    "user.name=Test",
+                    "-c",
+                    "user.email=test@example.com",
+                    "-c",
+                    "commit.gpgsign=false",
+                    "-c",
+                    "tag.gpgsign=false",
+                ])
+                .args(args)
+                .status()
+                .expect("Couldn't run git");
+            assert!(status.success(), "git {:?} failed", args);
+        }
+    }
+
+    #[test]
+    fn release_type_of_commit_headers() {
+        assert_eq!(release_type("fix: a bug"), Some(ReleaseType::Patch));
+        assert_eq!(
+            release_type("perf(parser): faster"),
+            Some(ReleaseType::Patch)
+        );
+        assert_eq!(release_type("feat(cli): a flag"), Some(ReleaseType::Minor));
+        assert_eq!(release_type("feat!: a change"), Some(ReleaseType::Major));
+        assert_eq!(
+            release_type("refactor(api)!: a change"),
+            Some(ReleaseType::Major)
+        );
+        assert_eq!(release_type("docs: readme"), None);
+        assert_eq!(release_type("Merge branch 'main'"), None);
+        assert_eq!(release_type("feat(cli: unclosed scope"), None);
+    }
+
+    #[test]
+    fn release_type_of_breaking_change_footer() {
+        let message = "fix: a bug\n\nBREAKING CHANGE: the api changed";
+
+        assert_eq!(release_type(message), Some(ReleaseType::Major));
+    }
+
+    #[test]
+    fn parse_conventional_commit() {
+        let commit = ConventionalCommit::parse("feat(cli)!: a flag\n\nmore detail");
+
+        assert_eq!(
+            commit,
+            Some(ConventionalCommit {
+                typ: "feat",
+                scope: Some("cli"),
+                subject: "a flag",
+                breaking: true,
+            })
+        );
+    }
+
+    #[test]
+    fn bump_applies_zero_major_rules() {
+        let version = Version::new(0, 2, 3);
+
+        assert_eq!(bump(&version, ReleaseType::Major), Version::new(0, 3, 0));
+        assert_eq!(bump(&version, ReleaseType::Minor), Version::new(0, 3, 0));
+        assert_eq!(bump(&version, ReleaseType::Patch), Version::new(0, 2, 4));
+        assert_eq!(
+            bump(&Version::new(1, 2, 3), ReleaseType::Major),
+            Version::new(2, 0, 0)
+        );
+    }
+
+    #[test]
+    fn next_version_since_last_release_tag() {
+        let repo = FixtureRepo::new();
+        repo.commit("feat: first")
+            .tag("v1.2.3")
+            .commit("fix: a bug")
+            .commit("docs: readme");
+
+        assert_eq!(repo.next_version().as_deref(), Some("1.2.4"));
+
+        repo.commit("feat(cli)!: new flags");
+
+        assert_eq!(repo.next_version().as_deref(), Some("2.0.0"));
+    }
+
+    #[test]
+    fn next_version_of_zero_major_release() {
+        let repo = FixtureRepo::new();
+        repo.commit("feat: first")
+            .tag("v0.4.1")
+            .commit("fix: a bug\n\nBREAKING CHANGE: removed an api");
+
+        assert_eq!(repo.next_version().as_deref(), Some("0.5.0"));
+    }
+
+    #[test]
+    fn next_version_without_release_tag_is_first_release() {
+        let repo = FixtureRepo::new();
+        repo.commit("chore: setup").commit("fix: a bug");
+
+        assert_eq!(repo.next_version().as_deref(), Some("1.0.0"));
+    }
+
+    #[test]
+    fn next_version_ignores_prerelease_and_foreign_tags() {
+        let repo = FixtureRepo::new();
+        repo.commit("feat: first")
+            .tag("v1.0.0")
+            .commit("feat: second")
+            .tag("v2.0.0-beta.1")
+            .tag("other-3.0.0")
+            .commit("fix: a bug");
+
+        assert_eq!(repo.next_version().as_deref(), Some("1.1.0"));
+    }
+
+    #[test]
+    fn next_version_without_releasable_commits_is_none() {
+        let repo = FixtureRepo::new();
+        repo.commit("feat: first")
+            .tag("v1.0.0")
+            .commit("chore: tidy");
+
+        assert_eq!(repo.next_version(), None);
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Release configuration read from the metadata of the workspace.
+//!
+//! The configuration lives in the `[workspace.metadata.semantic-release-cargo]` table
+//! of the root `Cargo.toml` file:
+//!
+//! ```toml
+//! [workspace.metadata.semantic-release-cargo]
+//! main-crate = "my-crate"
+//! docs-rs = true
+//! tag-format = "{name}-v{version}"
+//! commit-message = "chore(release): {version}"
+//!
+//! [workspace.metadata.semantic-release-cargo.registries.internal]
+//! url = "https://crates.internal/crates/{name}/{version}"
+//! ```
+//!
+//! Each crate can also have configuration in the `[package.metadata.semantic-release-cargo]`
+//! table of its own `Cargo.toml` file:
+//!
+//! ```toml
+//! [package.metadata.semantic-release-cargo]
+//! changelog = "docs/CHANGELOG.md"
+//! ```
+
+use std::collections::HashMap;
+
+use guppy::graph::{PackageGraph, PackageMetadata};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{tags, Error, Result};
+
+/// The key of the release configuration in the workspace metadata.
+const METADATA_KEY: &str = "semantic-release-cargo";
+
+/// The name under which `crates.io` is configured in the `registries` table.
+const CRATES_IO: &str = "crates-io";
+
+/// The release configuration of a workspace.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct WorkspaceConfig {
+    /// Per-registry configuration keyed by the registry name.
+    pub(crate) registries: HashMap<String, RegistryConfig>,
+
+    /// Whether to include docs.rs links for crates published to `crates.io`.
+    pub(crate) docs_rs: Option<bool>,
+
+    /// The crate that is linked in the release record.
+    pub(crate) main_crate: Option<String>,
+
+    /// The format of the tag of each released crate.
+    pub(crate) tag_format: Option<String>,
+
+    /// The template for the message of the commit made by `prepare`.
+    pub(crate) commit_message: Option<String>,
+}
+
+/// The configuration for a single registry.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct RegistryConfig {
+    /// The template for the web url of a published crate.
+    pub(crate) url: Option<String>,
+}
+
+impl WorkspaceConfig {
+    /// Read the release configuration from the workspace metadata of `graph`.
+    ///
+    /// A workspace without any release configuration has the default configuration.
+    pub(crate) fn from_graph(graph: &PackageGraph) -> Result<Self> {
+        match graph.workspace().metadata_table().get(METADATA_KEY) {
+            Some(table) => Self::deserialize(table)
+                .map_err(|err| Error::metadata_error(err, graph.workspace().root())),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The configured web url template for crates published to `registry` (or to
+    /// `crates.io` if `registry` is `None`).
+    pub(crate) fn url_template(&self, registry: Option<&str>) -> Option<&str> {
+        self.registries
+            .get(registry.unwrap_or(CRATES_IO))
+            .and_then(|config| config.url.as_deref())
+    }
+
+    /// Check that all of the configured url templates are valid.
+    pub(crate) fn validate(&self) -> Result<()> {
+        for config in self.registries.values() {
+            if let Some(template) = config.url.as_deref() {
+                UrlTemplate::parse(template)?;
+            }
+        }
+        if let Some(format) = self.tag_format.as_deref() {
+            tags::validate_format(format)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The release configuration of a single crate.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct PackageConfig {
+    /// The path of the changelog of the crate relative to its directory.
+    pub(crate) changelog: Option<String>,
+}
+
+impl PackageConfig {
+    /// Read the release configuration from the package metadata of `package`.
+    ///
+    /// A crate without any release configuration has the default configuration.
+    pub(crate) fn from_package(package: &PackageMetadata) -> Result<Self> {
+        match package.metadata_table().get(METADATA_KEY) {
+            Some(table) => Self::deserialize(table).map_err(|err| {
+                let dir = package
+                    .manifest_path()
+                    .parent()
+                    .unwrap_or(package.manifest_path());
+                Error::metadata_error(err, dir)
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// A template for the web url of a published crate.
+///
+/// The placeholders `{name}` and `{version}` are replaced by the name and version of
+/// the crate.
+#[derive(Debug, Clone)]
+pub(crate) struct UrlTemplate {
+    template: String,
+}
+
+impl UrlTemplate {
+    /// Parse a url template, checking that it renders to a valid url.
+    pub(crate) fn parse(template: &str) -> Result<Self> {
+        let template = Self {
+            template: template.to_owned(),
+        };
+        template.render("crate-name", "0.0.0")?;
+
+        Ok(template)
+    }
+
+    /// Render the url for a version of a crate.
+    pub(crate) fn render(&self, name: &str, version: &str) -> Result<Url> {
+        let url = self
+            .template
+            .replace("{name}", name)
+            .replace("{version}", version);
+
+        Url::parse(&url).map_err(Error::url_parse_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn url_template_renders_name_and_version() {
+        let template = UrlTemplate::parse("https://crates.internal/crates/{name}/{version}")
+            .expect("valid template rejected");
+
+        let url = template.render("dep1", "2.0.0").expect("unable to render");
+
+        assert_eq!(url.as_str(), "https://crates.internal/crates/dep1/2.0.0");
+    }
+
+    #[test]
+    fn url_template_without_scheme_is_error() {
+        let result = UrlTemplate::parse("crates.internal/crates/{name}");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn workspace_config_reads_registry_templates() {
+        let config = WorkspaceConfig::deserialize(json!({
+            "main-crate": "dep1",
+            "docs-rs": true,
+            "registries": {
+                "internal": { "url": "https://crates.internal/{name}" },
+            },
+        }))
+        .expect("unable to deserialize config");
+
+        assert_eq!(
+            config.url_template(Some("internal")),
+            Some("https://crates.internal/{name}")
+        );
+        assert_eq!(config.url_template(None), None);
+        assert_eq!(config.docs_rs, Some(true));
+        assert_eq!(config.main_crate.as_deref(), Some("dep1"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn package_config_reads_changelog_path() {
+        let config = PackageConfig::deserialize(json!({ "changelog": "docs/CHANGES.md" }))
+            .expect("unable to deserialize config");
+
+        assert_eq!(config.changelog.as_deref(), Some("docs/CHANGES.md"));
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The directory and environment in which a release step runs.
+//!
+//! Under `semantic-release` these come from the `cwd` and `env` of the context of the
+//! step rather than from the Node process. The cargo configuration, and so the registry
+//! tokens, is read for that directory and environment, and the cargo commands run in
+//! them.
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use cargo_config2::ResolveOptions;
+
+use crate::runner::{CargoInvocation, CargoRunner, ProcessCargoRunner};
+use crate::{Error, Result};
+
+/// The directory and environment in which a release step runs.
+///
+/// The default context is the current directory and environment of the process, with
+/// the cargo commands run as child processes.
+#[derive(Debug, Clone)]
+pub(crate) struct StepContext {
+    cwd: Option<PathBuf>,
+    env: HashMap<String, String>,
+    runner: Arc<dyn CargoRunner>,
+}
+
+impl Default for StepContext {
+    fn default() -> Self {
+        StepContext::new(None, HashMap::new())
+    }
+}
+
+impl StepContext {
+    /// A context that runs in `cwd` with the variables in `env` added to, and
+    /// overriding, the environment of the process.
+    pub(crate) fn new(cwd: Option<PathBuf>, env: HashMap<String, String>) -> Self {
+        StepContext {
+            cwd,
+            env,
+            runner: Arc::new(ProcessCargoRunner::new()),
+        }
+    }
+
+    /// This context with its cargo commands run by `runner`.
+    pub(crate) fn with_runner(self, runner: Arc<dyn CargoRunner>) -> Self {
+        StepContext { runner, ..self }
+    }
+
+    /// The runner of the cargo commands of the context.
+    pub(crate) fn runner(&self) -> &dyn CargoRunner {
+        self.runner.as_ref()
+    }
+
+    /// The path to the manifest of the root of the workspace.
+    ///
+    /// A relative `manifest_path` is relative to the directory of the context. Without
+    /// a `manifest_path` the manifest is the `Cargo.toml` file in that directory, or is
+    /// left for cargo to find if the context has no directory of its own.
+    #[cfg_attr(not(feature = "napi-rs"), allow(dead_code))]
+    pub(crate) fn manifest_path(&self, manifest_path: Option<&str>) -> Option<PathBuf> {
+        match (self.cwd.as_deref(), manifest_path) {
+            (Some(cwd), Some(path)) => Some(cwd.join(path)),
+            (Some(cwd), None) => Some(cwd.join("Cargo.toml")),
+            (None, path) => path.map(PathBuf::from),
+        }
+    }
+
+    /// Load the cargo configuration for the directory and environment of the context.
+    pub(crate) fn cargo_config(&self) -> Result<cargo_config2::Config> {
+        let cwd = match self.cwd.as_deref() {
+            Some(cwd) => cwd.to_path_buf(),
+            None => env::current_dir().map_err(Error::current_dir_error)?,
+        };
+        let vars = env::vars_os().chain(
+            self.env
+                .iter()
+                .map(|(key, value)| (OsString::from(key), OsString::from(value))),
+        );
+
+        cargo_config2::Config::load_with_options(&cwd, ResolveOptions::default().env(vars))
+            .map_err(|err| Error::cargo_config_error(err, &cwd))
+    }
+
+    /// The `cargo` command with `args` for the crate `package`, to run in `dir` or else
+    /// in the directory of the context, and in the environment of the context.
+    pub(crate) fn invocation(
+        &self,
+        package: Option<&str>,
+        args: Vec<String>,
+        dir: Option<&Path>,
+    ) -> CargoInvocation {
+        CargoInvocation {
+            package: package.map(ToOwned::to_owned),
+            args,
+            current_dir: dir.or(self.cwd.as_deref()).map(Path::to_path_buf),
+            env: self.env.clone(),
+            timeout: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_path_is_relative_to_cwd() {
+        let context = StepContext::new(Some(PathBuf::from("/work")), HashMap::new());
+
+        assert_eq!(
+            context.manifest_path(Some("crates/Cargo.toml")),
+            Some(PathBuf::from("/work/crates/Cargo.toml"))
+        );
+        assert_eq!(
+            context.manifest_path(None),
+            Some(PathBuf::from("/work/Cargo.toml"))
+        );
+        assert_eq!(StepContext::default().manifest_path(None), None);
+    }
+
+    #[test]
+    fn cargo_config_reads_tokens_from_env() {
+        let cwd = tempfile::tempdir().expect("Couldn't create temp dir");
+        let env = [(
+            "CARGO_REGISTRIES_INTERNAL_TOKEN".to_owned(),
+            "secret".to_owned(),
+        )]
+        .into_iter()
+        .collect();
+        let context = StepContext::new(Some(cwd.path().to_path_buf()), env);
+
+        let config = context.cargo_config().expect("cargo_config failed");
+
+        let registry = config
+            .registries
+            .get("internal")
+            .or_else(|| config.registries.get("INTERNAL"))
+            .expect("no internal registry");
+        assert!(registry.token.is_some());
+    }
+}
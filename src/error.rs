@@ -10,6 +10,7 @@ use std::{
     io,
     path::{Path, PathBuf},
     process::ExitStatus,
+    time::Duration,
 };
 
 use guppy::errors::Error as GuppyError;
@@ -18,10 +19,14 @@ use thiserror::Error;
 use toml_edit::TomlError as TomlEditError;
 use url::ParseError;
 
-use super::DependencyType;
+use super::{DependencyType, PublishReport, YankReport, YankStatus};
 
 /// The error type for operations `semantic-release-rust` operations.
+///
+/// New kinds of errors, and new fields of the existing kinds, may be added without a
+/// major release, so matches on an `Error` need a wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum Error {
     /// Error while parsing the structure of a workspace.
     #[error(transparent)]
@@ -29,6 +34,7 @@ pub enum Error {
 
     /// Error when verifying that a workspace does not include cycles.
     #[error("Workspace has at least one cycle that includes as least {crate1} and {crate2}")]
+    #[non_exhaustive]
     WorkspaceCycles {
         /// The first crate in the cycle.
         crate1: String,
@@ -39,6 +45,7 @@ pub enum Error {
 
     /// Error while verifying the conditions for a release.
     #[error("Conditions for a release are not satisfied: {reason}")]
+    #[non_exhaustive]
     VerifyError {
         /// The reason the conditions are not satisfied.
         reason: String,
@@ -48,6 +55,7 @@ pub enum Error {
     ///
     /// This is a specific part of verifying the conditions for a release.
     #[error("{typ} of {from} on {to} prevents publication of {from}")]
+    #[non_exhaustive]
     BadDependency {
         /// The name of the package whose dependency prevents publication.
         from: String,
@@ -59,8 +67,17 @@ pub enum Error {
         typ: DependencyType,
     },
 
+    /// Error when a selected package is not a member of the workspace.
+    #[error("Package {name} is not a member of the workspace")]
+    #[non_exhaustive]
+    UnknownPackage {
+        /// The name of the selected package.
+        name: String,
+    },
+
     /// Error while reading a file.
     #[error("Unable to read file {}", path.display())]
+    #[non_exhaustive]
     FileReadError {
         /// The underlying error.
         #[source]
@@ -72,6 +89,7 @@ pub enum Error {
 
     /// Error while writing a file.
     #[error("Unable to write file {}", path.display())]
+    #[non_exhaustive]
     FileWriteError {
         /// The underlying error.
         #[source]
@@ -81,12 +99,38 @@ pub enum Error {
         path: PathBuf,
     },
 
+    /// Error while finding the current directory.
+    #[error("Unable to get the current directory")]
+    #[non_exhaustive]
+    CurrentDirError {
+        /// The underlying error.
+        #[source]
+        inner: io::Error,
+    },
+
+    /// Error while reading the cargo configuration.
+    #[error("Unable to read the cargo configuration for {}", cwd.display())]
+    #[non_exhaustive]
+    CargoConfigError {
+        /// The underlying error.
+        #[source]
+        inner: cargo_config2::Error,
+
+        /// The directory for which the configuration was read.
+        cwd: PathBuf,
+    },
+
     /// Error while parsing a TOML document.
     #[error(transparent)]
     TomlError(TomlError),
 
+    /// Error while reading the release configuration from the workspace metadata.
+    #[error(transparent)]
+    MetadataError(MetadataError),
+
     /// Error while examining the contents of a `Cargo.toml` file.
     #[error("Unexpected contents of {manifest_path}")]
+    #[non_exhaustive]
     CargoTomlError {
         /// The error found in the `Cargo.toml` file.
         #[source]
@@ -98,6 +142,7 @@ pub enum Error {
 
     /// Error while attempting to run `cargo publish`
     #[error("Unable to run \"cargo publish\" for {manifest_path}")]
+    #[non_exhaustive]
     CargoPublish {
         /// The underlying error.
         #[source]
@@ -109,6 +154,7 @@ pub enum Error {
 
     /// Error that records a non-sucess exit status from `cargo publish`.
     #[error("\"cargo publish\" exited with a failure for {manifest_path}: {status}\n{stderr}")]
+    #[non_exhaustive]
     CargoPublishStatus {
         /// The exit status from `cargo publish`.
         status: ExitStatus,
@@ -120,6 +166,127 @@ pub enum Error {
         stderr: String,
     },
 
+    /// Error when `cargo publish` for a crate did not finish within its timeout.
+    #[error("\"cargo publish\" did not finish within {} seconds for {manifest_path}", timeout.as_secs())]
+    #[non_exhaustive]
+    CargoPublishTimeout {
+        /// The time that `cargo publish` was allowed to take.
+        timeout: Duration,
+
+        /// The manifest path for the crate on which the error occurred.
+        manifest_path: PathBuf,
+    },
+
+    /// Error when a crate of the workspace failed to publish, or when the published
+    /// crates could not be tagged.
+    ///
+    /// The report lists the outcome for each crate, as it would on success, so that
+    /// the crates that were published before the failure are known.
+    #[error("Unable to finish publishing the workspace\n{}", report.summary())]
+    #[non_exhaustive]
+    PublishFailed {
+        /// The outcome for each crate of the workspace.
+        report: Box<PublishReport>,
+
+        /// The error for the crate that failed, or for the tags.
+        #[source]
+        inner: Box<Error>,
+    },
+
+    /// Error while attempting to run `cargo yank`.
+    #[error("Unable to run \"cargo yank\" for {name}")]
+    #[non_exhaustive]
+    CargoYank {
+        /// The underlying error.
+        #[source]
+        inner: io::Error,
+
+        /// The name of the crate on which the error occurred.
+        name: String,
+    },
+
+    /// Error that records a non-success exit status from `cargo yank`.
+    #[error("\"cargo yank\" exited with a failure for {name}: {status}\n{stderr}")]
+    #[non_exhaustive]
+    CargoYankStatus {
+        /// The exit status from `cargo yank`.
+        status: ExitStatus,
+
+        /// The name of the crate on which the error occurred.
+        name: String,
+
+        /// The stderr output from cargo yank.
+        stderr: String,
+    },
+
+    /// Error when a crate of the workspace failed to be yanked (or un-yanked).
+    ///
+    /// The report lists the outcome for each crate, as it would on success.
+    #[error("Unable to {} version {} of {}", if report.undo { "un-yank" } else { "yank" }, report.version, failed_yanks(report))]
+    #[non_exhaustive]
+    YankFailed {
+        /// The outcome for each crate of the workspace.
+        report: Box<YankReport>,
+
+        /// The error for the first crate that failed.
+        #[source]
+        inner: Box<Error>,
+    },
+
+    /// Error while attempting to run `git`.
+    #[error("Unable to run \"git {args}\"")]
+    #[non_exhaustive]
+    GitError {
+        /// The underlying error.
+        #[source]
+        inner: io::Error,
+
+        /// The arguments to `git`.
+        args: String,
+    },
+
+    /// Error that records a non-success exit status from `git`.
+    #[error("\"git {args}\" exited with a failure: {status}\n{stderr}")]
+    #[non_exhaustive]
+    GitStatus {
+        /// The exit status from `git`.
+        status: ExitStatus,
+
+        /// The arguments to `git`.
+        args: String,
+
+        /// The stderr output from git.
+        stderr: String,
+    },
+
+    /// Error when the tag of a crate already exists and points at another commit.
+    #[error("Tag {tag} already exists and points at {commit}, not HEAD")]
+    #[non_exhaustive]
+    TagConflict {
+        /// The name of the tag.
+        tag: String,
+
+        /// The commit that the tag points at.
+        commit: String,
+    },
+
+    /// Error when a tag format does not give a different tag for each version.
+    #[error("Tag format {format:?} does not include {{version}}")]
+    #[non_exhaustive]
+    TagFormatError {
+        /// The tag format.
+        format: String,
+    },
+
+    /// Error when a log level is not one of `error`, `warn`, `info`, `debug` or
+    /// `trace`.
+    #[error("Unknown log level {level:?}")]
+    #[non_exhaustive]
+    LogLevelError {
+        /// The log level.
+        level: String,
+    },
+
     /// Error while parsing a url for the release record.
     #[error(transparent)]
     UrlError(UrlError),
@@ -128,8 +295,26 @@ pub enum Error {
     #[error(transparent)]
     WriteReleaseError(WriteReleaseError),
 
+    /// Error when the workspace does not match the release plan being applied.
+    #[error("The workspace does not match the release plan: {reason}")]
+    #[non_exhaustive]
+    PlanMismatch {
+        /// The way in which the workspace does not match the plan.
+        reason: String,
+    },
+
+    /// Error while reading or resuming the journal of an interrupted publish, or when
+    /// a new publish would overwrite it.
+    #[error("Unable to use the publish journal: {reason}")]
+    #[non_exhaustive]
+    JournalError {
+        /// The reason the journal could not be used.
+        reason: String,
+    },
+
     /// Error while attempting to update Cargo lockfile.
     #[error("Unable to update Cargo lockfile")]
+    #[non_exhaustive]
     CargoLockfileUpdate {
         /// The reason for the failed lockfile update.
         reason: String,
@@ -139,10 +324,7 @@ pub enum Error {
 }
 
 /// A specialized `Result` type for `semantic-release-cargo` operations.
-// #[cfg(feature = "napi-rs")]
-pub type Result<T> = std::result::Result<T, anyhow::Error>;
-// #[cfg(not(feature = "napi-rs"))]
-// pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = std::result::Result<T, Error>;
 
 /// The error details related to a problem parsing the workspace structure.
 #[derive(Debug, Error)]
@@ -162,11 +344,23 @@ pub struct TomlError {
     path: PathBuf,
 }
 
+/// The error details related to a problem reading the release configuration from the
+/// workspace metadata.
+#[derive(Debug, Error)]
+#[error("Unable to read the semantic-release-cargo metadata of the workspace at {}", workspace_root.display())]
+pub struct MetadataError {
+    #[source]
+    inner: serde_json::Error,
+    workspace_root: PathBuf,
+}
+
 /// The error details related the contents of a `Cargo.toml` file.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum CargoTomlError {
     /// Error related to a missing table in a `Cargo.toml` file.
     #[error("Unable to locate expected table {table_name}")]
+    #[non_exhaustive]
     NoTable {
         /// The name of the missing table.
         table_name: String,
@@ -174,6 +368,7 @@ pub enum CargoTomlError {
 
     /// Error related to a missing value in a `Cargo.toml` file.
     #[error("Unable to located expected value {value_name}")]
+    #[non_exhaustive]
     NoValue {
         /// The name of the missing value.
         value_name: String,
@@ -181,6 +376,7 @@ pub enum CargoTomlError {
 
     /// Error related to failed attempt to set the version for a package or a dependency.
     #[error("Unable to set the version for {name} to {version}")]
+    #[non_exhaustive]
     SetVersion {
         /// The name of the package or dependency.
         name: String,
@@ -209,6 +405,60 @@ pub struct WriteReleaseError {
 }
 
 impl Error {
+    /// A short identifier of the kind of error, such as `EVERIFY`.
+    ///
+    /// This is the `code` of the errors that the napi steps reject with. A failed
+    /// publish or yank has the code of the error for the crate that failed.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::WorkspaceError(_) => "EWORKSPACE",
+            Error::WorkspaceCycles { .. } => "EWORKSPACECYCLES",
+            Error::VerifyError { .. } => "EVERIFY",
+            Error::BadDependency { .. } => "EBADDEPENDENCY",
+            Error::UnknownPackage { .. } => "EUNKNOWNPACKAGE",
+            Error::FileReadError { .. } => "EFILEREAD",
+            Error::FileWriteError { .. } => "EFILEWRITE",
+            Error::CurrentDirError { .. } => "ECURRENTDIR",
+            Error::CargoConfigError { .. } => "ECARGOCONFIG",
+            Error::TomlError(_) => "ETOML",
+            Error::MetadataError(_) => "EMETADATA",
+            Error::CargoTomlError { .. } => "ECARGOTOML",
+            Error::CargoPublish { .. } => "ECARGOPUBLISH",
+            Error::CargoPublishStatus { .. } => "ECARGOPUBLISHSTATUS",
+            Error::CargoPublishTimeout { .. } => "ECARGOPUBLISHTIMEOUT",
+            Error::PublishFailed { inner, .. } => inner.code(),
+            Error::CargoYank { .. } => "ECARGOYANK",
+            Error::CargoYankStatus { .. } => "ECARGOYANKSTATUS",
+            Error::YankFailed { inner, .. } => inner.code(),
+            Error::GitError { .. } => "EGIT",
+            Error::GitStatus { .. } => "EGITSTATUS",
+            Error::TagConflict { .. } => "ETAGCONFLICT",
+            Error::TagFormatError { .. } => "ETAGFORMAT",
+            Error::LogLevelError { .. } => "ELOGLEVEL",
+            Error::UrlError(_) => "EURL",
+            Error::WriteReleaseError(_) => "EWRITERELEASE",
+            Error::PlanMismatch { .. } => "EPLANMISMATCH",
+            Error::JournalError { .. } => "EJOURNAL",
+            Error::CargoLockfileUpdate { .. } => "ECARGOLOCKFILE",
+        }
+    }
+
+    /// The report of a publish that failed for some of the crates of the workspace.
+    pub fn publish_report(&self) -> Option<&PublishReport> {
+        match self {
+            Error::PublishFailed { report, .. } => Some(report),
+            _ => None,
+        }
+    }
+
+    /// The report of a yank that failed for some of the crates of the workspace.
+    pub fn yank_report(&self) -> Option<&YankReport> {
+        match self {
+            Error::YankFailed { report, .. } => Some(report),
+            _ => None,
+        }
+    }
+
     pub(crate) fn workspace_error(metadata_error: GuppyError, manifest_path: PathBuf) -> Error {
         Error::WorkspaceError(WorkspaceError {
             metadata_error,
@@ -230,6 +480,12 @@ impl Error {
         }
     }
 
+    pub(crate) fn unknown_package(name: &str) -> Error {
+        Error::UnknownPackage {
+            name: name.to_owned(),
+        }
+    }
+
     pub(crate) fn file_read_error(inner: io::Error, path: impl AsRef<Path>) -> Error {
         Error::FileReadError {
             inner,
@@ -244,6 +500,17 @@ impl Error {
         }
     }
 
+    pub(crate) fn current_dir_error(inner: io::Error) -> Error {
+        Error::CurrentDirError { inner }
+    }
+
+    pub(crate) fn cargo_config_error(inner: cargo_config2::Error, cwd: impl AsRef<Path>) -> Error {
+        Error::CargoConfigError {
+            inner,
+            cwd: cwd.as_ref().to_owned(),
+        }
+    }
+
     pub(crate) fn toml_error(inner: TomlEditError, path: impl AsRef<Path>) -> Error {
         Error::TomlError(TomlError {
             inner,
@@ -251,6 +518,28 @@ impl Error {
         })
     }
 
+    pub(crate) fn metadata_error(
+        inner: serde_json::Error,
+        workspace_root: impl AsRef<Path>,
+    ) -> Error {
+        Error::MetadataError(MetadataError {
+            inner,
+            workspace_root: workspace_root.as_ref().to_owned(),
+        })
+    }
+
+    pub(crate) fn plan_mismatch(reason: impl Into<String>) -> Error {
+        Error::PlanMismatch {
+            reason: reason.into(),
+        }
+    }
+
+    pub(crate) fn journal_error(reason: impl Into<String>) -> Error {
+        Error::JournalError {
+            reason: reason.into(),
+        }
+    }
+
     pub(crate) fn cargo_publish(inner: io::Error, manifest_path: &Path) -> Error {
         Error::CargoPublish {
             inner,
@@ -270,6 +559,74 @@ impl Error {
         }
     }
 
+    pub(crate) fn cargo_publish_timeout(timeout: Duration, manifest_path: &Path) -> Error {
+        Error::CargoPublishTimeout {
+            timeout,
+            manifest_path: manifest_path.to_owned(),
+        }
+    }
+
+    pub(crate) fn publish_failed(report: PublishReport, inner: Error) -> Error {
+        Error::PublishFailed {
+            report: Box::new(report),
+            inner: Box::new(inner),
+        }
+    }
+
+    pub(crate) fn yank_failed(report: YankReport, inner: Error) -> Error {
+        Error::YankFailed {
+            report: Box::new(report),
+            inner: Box::new(inner),
+        }
+    }
+
+    pub(crate) fn cargo_yank(inner: io::Error, name: &str) -> Error {
+        Error::CargoYank {
+            inner,
+            name: name.to_owned(),
+        }
+    }
+
+    pub(crate) fn cargo_yank_status(status: ExitStatus, name: &str, stderr: &[u8]) -> Error {
+        Error::CargoYankStatus {
+            status,
+            name: name.to_owned(),
+            stderr: String::from_utf8_lossy(stderr).into_owned(),
+        }
+    }
+
+    pub(crate) fn git_error(inner: io::Error, args: &[&str]) -> Error {
+        Error::GitError {
+            inner,
+            args: args.join(" "),
+        }
+    }
+
+    pub(crate) fn git_status(status: ExitStatus, args: &[&str], stderr: &[u8]) -> Error {
+        Error::GitStatus {
+            status,
+            args: args.join(" "),
+            stderr: String::from_utf8_lossy(stderr).into_owned(),
+        }
+    }
+
+    pub(crate) fn tag_conflict(tag: String, commit: String) -> Error {
+        Error::TagConflict { tag, commit }
+    }
+
+    pub(crate) fn tag_format_error(format: &str) -> Error {
+        Error::TagFormatError {
+            format: format.to_owned(),
+        }
+    }
+
+    #[cfg(feature = "napi-rs")]
+    pub(crate) fn log_level_error(level: &str) -> Error {
+        Error::LogLevelError {
+            level: level.to_owned(),
+        }
+    }
+
     pub(crate) fn url_parse_error(inner: ParseError) -> Error {
         Error::UrlError(UrlError { inner })
     }
@@ -282,6 +639,37 @@ impl Error {
     }
 }
 
+impl WorkspaceError {
+    /// The path to the `Cargo.toml` file from which the workspace was read.
+    pub fn manifest_path(&self) -> &Path {
+        &self.manifest_path
+    }
+
+    /// The error from reading the workspace with `cargo metadata`.
+    pub fn metadata_error(&self) -> &GuppyError {
+        &self.metadata_error
+    }
+}
+
+impl TomlError {
+    /// The path to the file that could not be parsed.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The error from parsing the file.
+    pub fn toml_error(&self) -> &TomlEditError {
+        &self.inner
+    }
+}
+
+impl UrlError {
+    /// The error from parsing the url.
+    pub fn parse_error(&self) -> ParseError {
+        self.inner
+    }
+}
+
 impl CargoTomlError {
     pub(crate) fn no_table(table: &str) -> Self {
         Self::NoTable {
@@ -309,3 +697,98 @@ impl CargoTomlError {
         }
     }
 }
+
+/// The names of the crates of `report` that failed to be yanked.
+fn failed_yanks(report: &YankReport) -> String {
+    let failed: Vec<_> = report
+        .crates
+        .iter()
+        .filter(|report| report.status == YankStatus::Failed)
+        .map(|report| report.name.as_str())
+        .collect();
+
+    failed.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use crate::{CrateReport, PublishStatus};
+
+    #[test]
+    fn error_format_includes_stderr() {
+        let error = Error::cargo_publish_status(
+            dummy_exit_status(),
+            Path::new("Cargo.toml"),
+            b"Error: failed to publish\nCause: package already exists on registry",
+        );
+
+        let error_string = format!("{}", error);
+        assert!(
+            error_string.contains("failed to publish"),
+            "Error should contain stderr content"
+        );
+        assert!(
+            error_string.contains("package already exists"),
+            "Error should contain all stderr content"
+        );
+    }
+
+    #[test]
+    fn error_code_names_the_kind_of_error() {
+        let error = Error::cargo_publish_status(dummy_exit_status(), Path::new("Cargo.toml"), b"");
+        let verify = Error::verify_error("no token");
+
+        assert_eq!(error.code(), "ECARGOPUBLISHSTATUS");
+        assert_eq!(verify.code(), "EVERIFY");
+    }
+
+    #[test]
+    fn failed_publish_has_the_code_of_the_crate_that_failed() {
+        let report = PublishReport {
+            name: "crates.io packages (0 packages published)".to_owned(),
+            url: None,
+            docs_url: None,
+            registry: "crates.io".to_owned(),
+            crates: vec![CrateReport::new(
+                "dep1",
+                "2.0.0",
+                "crates.io",
+                PublishStatus::Failed,
+            )],
+        };
+        let inner = Error::cargo_publish_status(dummy_exit_status(), Path::new("Cargo.toml"), b"");
+
+        let error = Error::publish_failed(report, inner);
+
+        assert_eq!(error.code(), "ECARGOPUBLISHSTATUS");
+        assert!(format!("{}", error).contains("Failed to publish dep1"));
+        assert_eq!(
+            error.publish_report().map(|report| report.crates.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn toml_error_names_the_file() {
+        let inner = "version = ".parse::<toml_edit::DocumentMut>().unwrap_err();
+
+        let Error::TomlError(error) = Error::toml_error(inner, "Cargo.toml") else {
+            panic!("not a TOML error");
+        };
+
+        assert_eq!(error.path(), Path::new("Cargo.toml"));
+        assert!(error.toml_error().span().is_some());
+    }
+
+    fn dummy_exit_status() -> ExitStatus {
+        if cfg!(windows) {
+            Command::new("cmd").args(["/C", "exit 101"]).status()
+        } else {
+            Command::new("sh").args(["-c", "exit 101"]).status()
+        }
+        .expect("Failed to execute command")
+    }
+}
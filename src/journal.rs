@@ -0,0 +1,264 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A journal of the crates published so far, for resuming an interrupted publish.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use guppy::graph::PackageGraph;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{transaction::write_atomic, Error, Result};
+
+/// The directory, within the target directory, that holds the journal.
+const JOURNAL_DIR: &str = "semantic-release-cargo";
+
+/// The file name of the journal.
+const JOURNAL_FILE: &str = "journal.json";
+
+/// The file name of the report of a failed release, next to the journal.
+const ROLLBACK_FILE: &str = "rollback.json";
+
+/// The journal of a publish run.
+///
+/// The journal is written after each crate is published and removed once every crate
+/// has been published.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    path: PathBuf,
+    record: Mutex<JournalRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JournalRecord {
+    registry: String,
+    published: Vec<JournalEntry>,
+}
+
+/// A crate recorded in the journal as published.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct JournalEntry {
+    pub(crate) name: String,
+    pub(crate) version: String,
+}
+
+impl Journal {
+    /// The path of the journal for the workspace of `graph`.
+    pub(crate) fn path(graph: &PackageGraph) -> PathBuf {
+        graph
+            .workspace()
+            .target_directory()
+            .as_std_path()
+            .join(JOURNAL_DIR)
+            .join(JOURNAL_FILE)
+    }
+
+    /// The path of the report of a failed release for the workspace of `graph`, which
+    /// is kept next to the journal.
+    pub(crate) fn rollback_path(graph: &PackageGraph) -> PathBuf {
+        Self::path(graph).with_file_name(ROLLBACK_FILE)
+    }
+
+    /// Start a new journal at `path` for publishing to `registry`.
+    ///
+    /// An existing journal at `path` is the only record of the crates that an
+    /// interrupted publish published, so it is an error for there to be one. The
+    /// publish can be resumed from it instead, or it can be removed.
+    pub(crate) fn new(path: PathBuf, registry: &str) -> Result<Self> {
+        match fs::metadata(&path) {
+            Ok(_) => {
+                return Err(Error::journal_error(format!(
+                    "the journal of an interrupted publish is at {}; run `semantic-release-cargo publish --resume` to continue that publish, or remove the journal to start a new one",
+                    path.display()
+                )))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(Error::file_read_error(err, &path)),
+        }
+
+        Ok(Self::empty(path, registry))
+    }
+
+    /// Resume the journal at `path` for publishing to `registry`.
+    ///
+    /// If there is no journal at `path` then nothing has been published yet and a new
+    /// journal is started.
+    pub(crate) fn resume(path: PathBuf, registry: &str) -> Result<Self> {
+        let Some(journal) = Self::read(&path)? else {
+            debug!("no journal at {}, starting a new one", path.display());
+            return Ok(Self::empty(path, registry));
+        };
+
+        let recorded = journal.registry();
+        if recorded != registry {
+            return Err(Error::journal_error(format!(
+                "the journal is for publishing to {}, not {}",
+                recorded, registry
+            )));
+        }
+
+        Ok(journal)
+    }
+
+    /// Read the journal at `path`, if there is one.
+    pub(crate) fn read(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::file_read_error(err, path)),
+        };
+
+        let record: JournalRecord = serde_json::from_str(&contents)
+            .map_err(|err| Error::journal_error(format!("{} ({})", err, path.display())))?;
+
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            record: Mutex::new(record),
+        }))
+    }
+
+    /// The registry to which the crates are published.
+    pub(crate) fn registry(&self) -> String {
+        self.lock().registry.clone()
+    }
+
+    /// The crates recorded as published.
+    pub(crate) fn published(&self) -> Vec<JournalEntry> {
+        self.lock().published.clone()
+    }
+
+    /// Check that the crates recorded as published are still members of the workspace
+    /// of `graph` with the same version.
+    pub(crate) fn verify(&self, graph: &PackageGraph) -> Result<()> {
+        for entry in &self.lock().published {
+            let pkg = graph.workspace().member_by_name(&entry.name).map_err(|_| {
+                Error::journal_error(format!(
+                    "{} is no longer a member of the workspace",
+                    entry.name
+                ))
+            })?;
+            if pkg.version().to_string() != entry.version {
+                return Err(Error::journal_error(format!(
+                    "the version of {} changed from {} to {}",
+                    entry.name,
+                    entry.version,
+                    pkg.version()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record that a crate was published and write the journal.
+    pub(crate) fn record(&self, name: &str, version: &str) -> Result<()> {
+        let mut record = self.lock();
+        record.published.push(JournalEntry {
+            name: name.to_owned(),
+            version: version.to_owned(),
+        });
+
+        let json = serde_json::to_string_pretty(&*record)
+            .map_err(|err| Error::journal_error(err.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|err| Error::file_write_error(err, dir))?;
+        }
+        write_atomic(&self.path, &json).map_err(|err| Error::file_write_error(err, &self.path))
+    }
+
+    /// Remove the journal once the publish run is complete.
+    pub(crate) fn complete(self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::file_write_error(err, &self.path)),
+        }
+    }
+
+    fn empty(path: PathBuf, registry: &str) -> Self {
+        Self {
+            path,
+            record: Mutex::new(JournalRecord {
+                registry: registry.to_owned(),
+                published: Vec::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, JournalRecord> {
+        self.record.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn recorded_crates_are_resumed() {
+        let dir = tempdir().expect("Couldn't create temp dir");
+        let path = dir.path().join("target").join("journal.json");
+        let journal = Journal::new(path.clone(), "crates.io").expect("unable to start");
+
+        journal.record("dep1", "2.0.0").expect("unable to record");
+        journal.record("build1", "2.0.0").expect("unable to record");
+        let resumed = Journal::resume(path, "crates.io").expect("unable to resume");
+
+        let names: Vec<_> = resumed.published().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["dep1", "build1"]);
+    }
+
+    #[test]
+    fn resume_for_another_registry_is_error() {
+        let dir = tempdir().expect("Couldn't create temp dir");
+        let path = dir.path().join("journal.json");
+        Journal::new(path.clone(), "crates.io")
+            .expect("unable to start")
+            .record("dep1", "2.0.0")
+            .expect("unable to record");
+
+        assert!(Journal::resume(path, "internal").is_err());
+    }
+
+    #[test]
+    fn complete_removes_the_journal() {
+        let dir = tempdir().expect("Couldn't create temp dir");
+        let path = dir.path().join("journal.json");
+        let journal = Journal::new(path.clone(), "crates.io").expect("unable to start");
+        journal.record("dep1", "2.0.0").expect("unable to record");
+
+        journal.complete().expect("unable to complete");
+
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn new_journal_over_an_earlier_one_is_error() {
+        let dir = tempdir().expect("Couldn't create temp dir");
+        let path = dir.path().join("journal.json");
+        Journal::new(path.clone(), "crates.io")
+            .expect("unable to start")
+            .record("dep1", "1.0.0")
+            .expect("unable to record");
+
+        let result = Journal::new(path.clone(), "crates.io");
+
+        assert_matches!(result, Err(Error::JournalError { .. }));
+        let resumed = Journal::resume(path, "crates.io").expect("unable to resume");
+        assert_eq!(resumed.published().len(), 1);
+    }
+}
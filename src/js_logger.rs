@@ -0,0 +1,118 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A logger that passes log records to a JavaScript callback.
+//!
+//! Under `semantic-release` the callback forwards each record to `context.logger`, so
+//! the output of the plugin carries the prefix of the plugin and can be captured like
+//! that of any other plugin. The logger can only be installed once per process, so
+//! registering a callback replaces the callback of an earlier registration.
+
+use std::{str::FromStr, sync::RwLock};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use napi::{
+    threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode},
+    Env,
+};
+use napi_derive::napi;
+
+use crate::Error;
+
+/// The log level of the JavaScript logger unless another one is given.
+const DEFAULT_LOG_LEVEL: Level = Level::Info;
+
+/// The callback that receives the name of a `context.logger` method and a message.
+type LogCallback = ThreadsafeFunction<(String, String), ErrorStrategy::Fatal>;
+
+/// The logger installed by the first registration.
+static JS_LOGGER: JsLogger = JsLogger {
+    callback: RwLock::new(None),
+};
+
+#[napi(object)]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `register_logger` function.
+pub struct LoggerOptions {
+    /// The most verbose level that is passed to the callback, one of `error`, `warn`,
+    /// `info`, `debug` or `trace` (defaults to `info`).
+    pub level: Option<String>,
+}
+
+/// Pass the log records of the plugin to a JavaScript callback.
+///
+/// The callback is called with the name of the `context.logger` method for the record,
+/// `error`, `warn` or `log`, and the message. Records at the `info`, `debug` and
+/// `trace` levels go to `log`. The callback does not keep the Node process alive.
+#[napi]
+pub fn register_logger(
+    env: Env,
+    #[napi(ts_arg_type = "(method: 'log' | 'warn' | 'error', message: string) => void")]
+    callback: LogCallback,
+    opts: Option<LoggerOptions>,
+) -> anyhow::Result<()> {
+    let level = match opts.and_then(|opts| opts.level) {
+        Some(level) => Level::from_str(&level).map_err(|_| Error::log_level_error(&level))?,
+        None => DEFAULT_LOG_LEVEL,
+    };
+
+    let mut callback = callback;
+    callback.unref(&env)?;
+    *JS_LOGGER
+        .callback
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = Some((level.to_level_filter(), callback));
+
+    // Only the first registration installs the logger; later ones replace its callback.
+    let _ = log::set_logger(&JS_LOGGER);
+    log::set_max_level(level.to_level_filter());
+
+    Ok(())
+}
+
+/// A logger that calls the registered JavaScript callback for each record.
+struct JsLogger {
+    callback: RwLock<Option<(LevelFilter, LogCallback)>>,
+}
+
+impl Log for JsLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.callback
+            .read()
+            .map(|callback| {
+                callback
+                    .as_ref()
+                    .is_some_and(|(max_level, _)| metadata.level() <= *max_level)
+            })
+            .unwrap_or_default()
+    }
+
+    fn log(&self, record: &Record) {
+        let Ok(callback) = self.callback.read() else {
+            return;
+        };
+        let Some((max_level, callback)) = callback.as_ref() else {
+            return;
+        };
+        if record.level() > *max_level {
+            return;
+        }
+
+        let method = match record.level() {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info | Level::Debug | Level::Trace => "log",
+        };
+        callback.call(
+            (method.to_owned(), record.args().to_string()),
+            ThreadsafeFunctionCallMode::NonBlocking,
+        );
+    }
+
+    fn flush(&self) {}
+}
@@ -8,67 +8,84 @@
 
 //! Implementation of the semantic release steps to for integrating a cargo-based Rust
 //! project.
+//!
+//! The API is the same with or without the `napi-rs` feature. The feature adds the
+//! `node` module with the napi bindings used by the `semantic-release` plugin.
 
 #![forbid(unsafe_code)]
 #![deny(warnings, missing_docs)]
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
     env, fmt, fs,
-    io::{BufRead, Cursor},
+    io::{self, BufRead, Cursor},
     path::{Path, PathBuf},
-    process::Command,
+    process::Output,
     result,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use guppy::{
+    errors::Error as GuppyError,
     graph::{DependencyDirection, PackageGraph, PackageLink, PackageMetadata, PackageSource},
-    MetadataCommand, PackageId,
+    CargoMetadata, PackageId,
 };
-use log::{debug, error, info, log, trace, Level};
-use serde::Serialize;
+use log::{debug, error, info, log, trace, warn, Level};
 use toml_edit::{DocumentMut, InlineTable, Item, Table, Value};
 use url::Url;
 
 #[cfg(feature = "napi-rs")]
 use napi_derive::napi;
 
+mod affected;
+mod changelog;
+mod commits;
+mod config;
+mod context;
 mod error;
+// Vendored from itertools and kept as it was written there.
+#[allow(clippy::collapsible_match, clippy::unnecessary_map_or)]
 mod itertools;
+mod journal;
+#[cfg(feature = "napi-rs")]
+mod js_logger;
 mod logger;
-
+#[cfg(feature = "napi-rs")]
+pub mod node;
+mod notes;
+mod plan;
+mod report;
+mod runner;
+mod tags;
+mod transaction;
+mod workspace;
+
+pub use affected::AffectedPackages;
 pub use error::{CargoTomlError, Error, Result};
 
 pub use logger::LoggerBuilder;
+pub use runner::{
+    CargoInvocation, CargoRunner, OutputStream, ProcessCargoRunner, RecordingCargoRunner,
+};
+pub use workspace::Workspace;
 
-use crate::itertools::Itertools;
+pub use plan::{EditKind, PlannedCrate, PlannedEdit, PlannedPublish, ReleasePlan};
 
-/// Verify that the conditions for a release are satisfied.
-///
-/// The conditions for a release checked by this function are:
-///
-///    1. That the cargo registry token has been defined.
-///    2. That it can construct the graph of all of the dependencies in the
-///       workspace.
-///    3. That the dependencies and build-dependencies of all of crates in the
-///       workspace are suitable for publishing to `crates.io`.
-///
-/// If `manifest_path` is provided then it is expect to give the path to the
-/// `Cargo.toml` file for the root of the workspace. If `manifest_path` is `None`
-/// then `verify_conditions` will look for the root of the workspace in a
-/// `Cargo.toml` file in the current directory. If one of the conditions for a
-/// release are not satisfied then an explanation for that will be written to
-/// `output`.
-///
-/// This implements the `verifyConditions` step for `semantic-release` for a
-/// Cargo-based rust workspace.
-#[cfg(feature = "napi-rs")]
-#[napi]
-pub fn verify_conditions() -> Result<()> {
-    let maybe_manifest_path: Option<&'static str> = None;
+pub use report::{
+    CrateReport, CrateTag, FailReport, PackageInfo, PublishReport, PublishStatus, PublishedCrate,
+    TagReport, TagStatus, YankReport, YankStatus, YankedCrate,
+};
 
-    internal_verify_conditions(None, maybe_manifest_path)
-}
+use crate::config::{PackageConfig, UrlTemplate, WorkspaceConfig};
+use crate::context::StepContext;
+use crate::itertools::Itertools;
+use crate::journal::Journal;
+use crate::transaction::Transaction;
 
 /// Verify that the conditions for a release are satisfied.
 ///
@@ -89,9 +106,12 @@ pub fn verify_conditions() -> Result<()> {
 ///
 /// This implements the `verifyConditions` step for `semantic-release` for a
 /// Cargo-based rust workspace.
-#[cfg(not(feature = "napi-rs"))]
 pub fn verify_conditions(manifest_path: Option<impl AsRef<Path>>) -> Result<()> {
-    internal_verify_conditions(None, manifest_path)
+    internal_verify_conditions(
+        manifest_path,
+        &VerifyArgs::default(),
+        &StepContext::default(),
+    )
 }
 
 /// Verify that the conditions for a release are satisfied.
@@ -118,20 +138,106 @@ pub fn verify_conditions(manifest_path: Option<impl AsRef<Path>>) -> Result<()>
 ///
 /// This implements the `verifyConditions` step for `semantic-release` for a
 /// Cargo-based rust workspace.
-#[cfg(not(feature = "napi-rs"))]
 pub fn verify_conditions_with_alternate(
     alternate_registry: Option<&str>,
     manifest_path: Option<impl AsRef<Path>>,
 ) -> Result<()> {
-    internal_verify_conditions(alternate_registry, manifest_path)
+    let args = VerifyArgs {
+        registry: alternate_registry.map(ToOwned::to_owned),
+        ..Default::default()
+    };
+
+    internal_verify_conditions(manifest_path, &args, &StepContext::default())
+}
+
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `verify_conditions_with_args` function.
+pub struct VerifyArgs {
+    /// The alternate registry to which the crates will be published.
+    pub registry: Option<String>,
+
+    /// The template for the web url of the published crates, overriding the one in
+    /// the workspace metadata.
+    pub url_template: Option<String>,
+
+    /// The crate linked in the release record, overriding the one in the workspace
+    /// metadata.
+    pub main_crate: Option<String>,
+}
+
+/// Verify that the conditions for a release are satisfied.
+///
+/// The conditions for a release checked by this function are those checked by
+/// [`verify_conditions_with_alternate`] for the registry in `args`, together with
+/// that the release configuration in the workspace metadata can be read and that any
+/// url templates for the release record are valid.
+///
+/// This implements the `verifyConditions` step for `semantic-release` for a
+/// Cargo-based rust workspace.
+pub fn verify_conditions_with_args(
+    manifest_path: Option<impl AsRef<Path>>,
+    args: &VerifyArgs,
+) -> Result<()> {
+    internal_verify_conditions(manifest_path, args, &StepContext::default())
+}
+
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// The directory and environment in which a step runs, as given by the context of the
+/// step under `semantic-release`.
+pub struct StepOptions {
+    /// The directory in which to run cargo and read the cargo configuration (defaults
+    /// to the current directory of the process).
+    pub cwd: Option<String>,
+
+    /// The path to the `Cargo.toml` file of the root of the workspace, relative to
+    /// `cwd` (defaults to the `Cargo.toml` file in `cwd`).
+    pub manifest_path: Option<String>,
+
+    /// The alternate registry to use when the arguments of the step do not name one.
+    pub registry: Option<String>,
+
+    /// Environment variables added to, and overriding, the environment of the process
+    /// when reading the registry tokens and running cargo.
+    pub env: Option<HashMap<String, String>>,
+}
+
+#[cfg(feature = "napi-rs")]
+impl StepOptions {
+    /// The context in which the step runs.
+    fn context(&self) -> StepContext {
+        StepContext::new(
+            self.cwd.as_ref().map(PathBuf::from),
+            self.env.clone().unwrap_or_default(),
+        )
+    }
+
+    /// The path to the manifest of the root of the workspace.
+    fn manifest_path(&self) -> Option<PathBuf> {
+        self.context().manifest_path(self.manifest_path.as_deref())
+    }
 }
 
 fn internal_verify_conditions(
-    alternate_registry: Option<&str>,
     manifest_path: Option<impl AsRef<Path>>,
+    args: &VerifyArgs,
+    context: &StepContext,
 ) -> Result<()> {
-    let cargo_config = cargo_config2::Config::load()?;
+    check_registry_token(&context.cargo_config()?, args.registry.as_deref())?;
+
+    debug!("Checking that workspace dependencies graph is buildable");
+    let workspace = Workspace::load(manifest_path.as_ref().map(AsRef::as_ref), context)?;
+
+    verify_workspace(workspace.graph(), workspace.config(), args)
+}
 
+/// Check that the registry token for `alternate_registry`, or for crates.io, is set in
+/// `cargo_config`.
+fn check_registry_token(
+    cargo_config: &cargo_config2::Config,
+    alternate_registry: Option<&str>,
+) -> Result<()> {
     let registry_token_set = match alternate_registry {
         Some(alternate_registry_id) => {
             // The key can be both uppercased or lowercased depending on the
@@ -146,7 +252,7 @@ fn internal_verify_conditions(
 
             registry_value.and_then(|registry| registry.token.as_ref().map(|_| ()))
         }
-        None => cargo_config.registry.token.map(|_| ()),
+        None => cargo_config.registry.token.as_ref().map(|_| ()),
     };
 
     debug!("Checking cargo registry token is set");
@@ -159,20 +265,38 @@ fn internal_verify_conditions(
         ))
     })?;
 
-    debug!("Checking that workspace dependencies graph is buildable");
-    let graph = get_package_graph(manifest_path)?;
+    Ok(())
+}
+
+/// Check that the workspace of `graph`, with the release configuration `config`, can
+/// be released with `args`.
+fn verify_workspace(
+    graph: &PackageGraph,
+    config: &WorkspaceConfig,
+    args: &VerifyArgs,
+) -> Result<()> {
+    let alternate_registry = args.registry.as_deref();
 
     debug!("Checking that the workspace does not contain any cycles");
     if let Some(cycle) = graph.cycles().all_cycles().next() {
         assert!(cycle.len() >= 2);
-        let crate0 = get_crate_name(&graph, cycle[0]);
-        let crate1 = get_crate_name(&graph, cycle[1]);
+        let crate0 = get_crate_name(graph, cycle[0]);
+        let crate1 = get_crate_name(graph, cycle[1]);
         let workspace_error = Error::WorkspaceCycles {
             crate1: crate0.to_owned(),
             crate2: crate1.to_owned(),
         };
 
-        return Err(workspace_error.into());
+        return Err(workspace_error);
+    }
+
+    debug!("Checking the release configuration");
+    config.validate()?;
+    if let Some(template) = args.url_template.as_deref() {
+        UrlTemplate::parse(template)?;
+    }
+    if let Some(main_crate) = args.main_crate.as_deref().or(config.main_crate.as_deref()) {
+        configured_main_crate(graph, main_crate, alternate_registry)?;
     }
 
     debug!("Checking that dependencies are suitable for publishing");
@@ -199,25 +323,190 @@ fn internal_verify_conditions(
     Ok(())
 }
 
-/// Prepare the Rust workspace for a release.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `verify_release` function.
+pub struct VerifyReleaseArgs {
+    /// The alternate registry to which the crates will be published.
+    pub registry: Option<String>,
+
+    /// The crates of the workspace to release (defaults to all of them).
+    pub packages: Option<Vec<String>>,
+
+    /// Whether to check with the registry that the new version of each crate has not
+    /// been published already (defaults to true).
+    pub check_registry: Option<bool>,
+}
+
+/// Verify that `next_release_version` can be released from the workspace.
 ///
-/// Preparing the release updates the version of each crate in the workspace and of
-/// the intra-workspace dependencies. The `version` field in the `packages` table of
-/// each `Cargo.toml` file in the workspace is set to the supplied version. The
-/// `version` field of each dependency, build-dependency and dev-dependency that
-/// is otherwise identified by a workspace-relative path dependencies is also set
-/// to the supplied version (the version filed will be added if it isn't
-/// already present).
+/// The version must be a valid semantic version that is not earlier than the current
+/// version of any of the publishable crates selected by `opts`. Unless the registry
+/// check is turned off, `cargo info` must also not find the version of any of those
+/// crates on the target registry. A registry that cannot be reached is logged and
+/// otherwise ignored.
 ///
-/// This implements the `prepare` step for `semantic-release` for a Cargo-based Rust
-/// workspace.
-#[cfg(feature = "napi-rs")]
-#[napi]
-pub fn prepare(next_release_version: String) -> Result<()> {
-    let manifest_path: Option<&Path> = None;
-    internal_prepare(manifest_path, next_release_version)
+/// This implements the `verifyRelease` step for `semantic-release` for a Cargo-based
+/// Rust workspace.
+pub fn verify_release(
+    manifest_path: Option<&Path>,
+    next_release_version: &str,
+    opts: &VerifyReleaseArgs,
+) -> Result<()> {
+    internal_verify_release(
+        manifest_path,
+        next_release_version,
+        opts,
+        &StepContext::default(),
+    )
+}
+
+fn internal_verify_release(
+    manifest_path: Option<&Path>,
+    next_release_version: &str,
+    opts: &VerifyReleaseArgs,
+    context: &StepContext,
+) -> Result<()> {
+    debug!("Building package graph");
+    let graph = get_package_graph_in(manifest_path, context)?;
+
+    verify_release_workspace(&graph, next_release_version, opts, context)
+}
+
+/// Verify that `next_release_version` can be released from the workspace of `graph`,
+/// running `cargo info` with the runner of `context`.
+fn verify_release_workspace(
+    graph: &PackageGraph,
+    next_release_version: &str,
+    opts: &VerifyReleaseArgs,
+    context: &StepContext,
+) -> Result<()> {
+    let next_version = semver::Version::parse(next_release_version).map_err(|err| {
+        Error::verify_error(format!(
+            "Version {} is not a valid semantic version: {}",
+            next_release_version, err
+        ))
+    })?;
+    let selection = PackageSelection::new(graph, opts.packages.as_deref())?;
+    let registry = opts.registry.as_deref();
+
+    for pkg in publishable_packages(graph, registry)
+        .into_iter()
+        .filter(|pkg| selection.contains(pkg.name()))
+    {
+        debug!("Checking the version of {}", pkg.name());
+        if *pkg.version() > next_version {
+            return Err(Error::verify_error(format!(
+                "{} is already at version {}, which is later than {}",
+                pkg.name(),
+                pkg.version(),
+                next_version
+            )));
+        }
+
+        if opts.check_registry.unwrap_or(true)
+            && version_is_published(context, pkg.name(), next_release_version, registry)
+        {
+            return Err(Error::verify_error(format!(
+                "Version {} of {} is already published to {}",
+                next_release_version,
+                pkg.name(),
+                registry.unwrap_or("crates.io")
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `cargo info` finds version `version` of the crate `name` on `registry`.
+///
+/// Failures other than not finding the version are logged and taken to mean that the
+/// version is not published.
+fn version_is_published(
+    context: &StepContext,
+    name: &str,
+    version: &str,
+    registry: Option<&str>,
+) -> bool {
+    let args = vec![
+        "info".to_owned(),
+        "--quiet".to_owned(),
+        format!("{}@{}", name, version),
+        "--registry".to_owned(),
+        registry.unwrap_or("crates-io").to_owned(),
+    ];
+    let invocation = context.invocation(Some(name), args, None);
+
+    trace!("running: {:?}", invocation);
+    let output = match context.runner().info(&invocation) {
+        Ok(output) => output,
+        Err(err) => {
+            warn!("Unable to run \"cargo info\" for {}: {}", name, err);
+            return false;
+        }
+    };
+
+    if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return stdout.lines().any(|line| {
+            line.strip_prefix("version: ")
+                .and_then(|rest| rest.split_whitespace().next())
+                == Some(version)
+        });
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.contains("could not find") {
+        warn!(
+            "Unable to check whether version {} of {} is published: {}",
+            version,
+            name,
+            stderr.trim()
+        );
+    }
+
+    false
 }
 
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `prepare` function.
+pub struct PrepareArgs {
+    /// The crates of the workspace to prepare (defaults to all of them).
+    pub packages: Option<Vec<String>>,
+
+    /// Whether to add a section for the release to the changelog of each prepared
+    /// crate.
+    pub changelog: Option<bool>,
+
+    /// The notes for the changelog of each crate, keyed by the name of the crate.
+    pub changelog_notes: Option<HashMap<String, String>>,
+
+    /// The git revision range, such as `v1.2.0..HEAD`, from which to generate the
+    /// notes of the crates that are not in `changelog_notes`.
+    pub changelog_range: Option<String>,
+
+    /// The date in the heading of the new sections (defaults to today in UTC, as
+    /// `YYYY-MM-DD`).
+    pub changelog_date: Option<String>,
+
+    /// Whether to leave the changelogs of crates without any notes untouched.
+    pub skip_unchanged: Option<bool>,
+
+    /// Whether to commit the files that were changed, and nothing else, to git.
+    pub commit: Option<bool>,
+
+    /// The template for the commit message, in which `{version}` is replaced by the
+    /// new version (defaults to `chore(release): {version}`), overriding the one in
+    /// the workspace metadata.
+    pub commit_message: Option<String>,
+}
+
+/// The template for the message of the commit made by `prepare` unless another one
+/// is configured.
+const DEFAULT_COMMIT_MESSAGE: &str = "chore(release): {version}";
+
 /// Prepare the Rust workspace for a release.
 ///
 /// Preparing the release updates the version of each crate in the workspace and of
@@ -228,17 +517,244 @@ pub fn prepare(next_release_version: String) -> Result<()> {
 /// to the supplied version (the version filed will be added if it isn't
 /// already present).
 ///
+/// All of the updated files are computed before any of them is written. If any
+/// update fails nothing is written, and if writing any file fails the files
+/// already written are restored.
+///
 /// This implements the `prepare` step for `semantic-release` for a Cargo-based Rust
 /// workspace.
-#[cfg(not(feature = "napi-rs"))]
 pub fn prepare(manifest_path: Option<&Path>, next_release_version: String) -> Result<()> {
-    internal_prepare(manifest_path, next_release_version)
+    internal_prepare(
+        manifest_path,
+        next_release_version,
+        &PrepareArgs::default(),
+        &StepContext::default(),
+    )
 }
 
-fn internal_prepare(manifest_path: Option<&Path>, next_release_version: String) -> Result<()> {
+/// Prepare the selected crates of the Rust workspace for a release.
+///
+/// This is the same as [`prepare`] except that only the crates selected by `opts`
+/// have their version set. The dependencies of other crates on the selected crates
+/// are still updated.
+pub fn prepare_with_args(
+    manifest_path: Option<&Path>,
+    next_release_version: String,
+    opts: &PrepareArgs,
+) -> Result<()> {
+    internal_prepare(
+        manifest_path,
+        next_release_version,
+        opts,
+        &StepContext::default(),
+    )
+}
+
+/// Compute the changes that preparing the Rust workspace for a release would make.
+///
+/// This runs the same updates as [`prepare`] against in-memory copies of the files in
+/// the workspace and returns a unified diff of the changes, without writing anything.
+/// The diff is empty if the workspace is already prepared for `next_release_version`.
+pub fn prepare_diff(
+    manifest_path: Option<&Path>,
+    next_release_version: &str,
+    opts: &PrepareArgs,
+) -> Result<String> {
+    internal_prepare_diff(
+        manifest_path,
+        next_release_version,
+        opts,
+        &StepContext::default(),
+    )
+}
+
+fn internal_prepare(
+    manifest_path: Option<&Path>,
+    next_release_version: String,
+    opts: &PrepareArgs,
+    context: &StepContext,
+) -> Result<()> {
+    debug!("Building package graph");
+    let workspace = Workspace::load(manifest_path, context)?;
+
+    prepare_workspace(
+        workspace.graph(),
+        workspace.config(),
+        &next_release_version,
+        opts,
+    )
+}
+
+/// Set the version of the selected crates of the workspace of `graph`, with the
+/// release configuration `config`, to `next_release_version`.
+fn prepare_workspace(
+    graph: &PackageGraph,
+    config: &WorkspaceConfig,
+    next_release_version: &str,
+    opts: &PrepareArgs,
+) -> Result<()> {
+    let selection = PackageSelection::new(graph, opts.packages.as_deref())?;
+
+    let mut transaction = prepare_transaction(graph, next_release_version, &selection)?;
+    if opts.changelog.unwrap_or_default() {
+        stage_changelogs(
+            graph,
+            next_release_version,
+            &selection,
+            opts,
+            &mut transaction,
+        )?;
+    }
+
+    let commit_message = if opts.commit.unwrap_or_default() {
+        let template = opts
+            .commit_message
+            .as_deref()
+            .or(config.commit_message.as_deref())
+            .unwrap_or(DEFAULT_COMMIT_MESSAGE);
+        Some(template.replace("{version}", next_release_version))
+    } else {
+        None
+    };
+
+    let changed = transaction.changed_paths();
+    debug!("Writing the updated manifests and lockfiles.");
+    transaction.commit()?;
+
+    match commit_message {
+        Some(_) if changed.is_empty() => info!("There are no changes to commit"),
+        Some(message) => {
+            let root = graph.workspace().root().as_std_path();
+            let commit = commits::commit_paths(root, &changed, &message)?;
+            info!("Committed the release changes as {}", commit);
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
+fn internal_prepare_diff(
+    manifest_path: Option<&Path>,
+    next_release_version: &str,
+    opts: &PrepareArgs,
+    context: &StepContext,
+) -> Result<String> {
     debug!("Building package graph");
-    let graph = get_package_graph(manifest_path)?;
+    let workspace = Workspace::load(manifest_path, context)?;
+
+    prepare_diff_workspace(workspace.graph(), next_release_version, opts)
+}
+
+/// The diff of the changes that setting the version of the selected crates of the
+/// workspace of `graph` to `next_release_version` would make.
+fn prepare_diff_workspace(
+    graph: &PackageGraph,
+    next_release_version: &str,
+    opts: &PrepareArgs,
+) -> Result<String> {
+    let selection = PackageSelection::new(graph, opts.packages.as_deref())?;
+
+    let mut transaction = prepare_transaction(graph, next_release_version, &selection)?;
+    if opts.changelog.unwrap_or_default() {
+        stage_changelogs(
+            graph,
+            next_release_version,
+            &selection,
+            opts,
+            &mut transaction,
+        )?;
+    }
+
+    Ok(transaction.diff(graph.workspace().root().as_std_path()))
+}
+
+/// Stage a new section for the release in the changelog of each selected crate of
+/// the workspace of `graph`.
+///
+/// The notes of a crate are taken from `opts.changelog_notes` or, failing that,
+/// generated from the commits in `opts.changelog_range`.
+fn stage_changelogs(
+    graph: &PackageGraph,
+    next_release_version: &str,
+    selection: &PackageSelection,
+    opts: &PrepareArgs,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    let generated = match &opts.changelog_range {
+        Some(range) => notes::crate_notes(graph, range)?,
+        None => HashMap::new(),
+    };
+    let date = opts.changelog_date.clone().unwrap_or_else(changelog::today);
+    let skip_unchanged = opts.skip_unchanged.unwrap_or_default();
+
+    debug!("Adding the release to the changelogs of the workspace.");
+    for package in graph.workspace().iter() {
+        if !selection.contains(package.name()) {
+            continue;
+        }
+
+        let notes = opts
+            .changelog_notes
+            .as_ref()
+            .and_then(|notes| notes.get(package.name()))
+            .or_else(|| generated.get(package.name()))
+            .map(String::as_str)
+            .unwrap_or_default();
+        if skip_unchanged && notes.trim().is_empty() {
+            debug!("skipping the changelog of unchanged {}", package.name());
+            continue;
+        }
 
+        let path = changelog_path(&package)?;
+        let original = match fs::read_to_string(&path) {
+            Ok(original) => Some(original),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(Error::file_read_error(err, &path)),
+        };
+
+        let Some(contents) =
+            changelog::add_section(original.as_deref(), next_release_version, &date, notes)
+        else {
+            debug!(
+                "{} already has a section for {}",
+                path.display(),
+                next_release_version
+            );
+            continue;
+        };
+        info!("Adding {} to {}", next_release_version, path.display());
+        match original {
+            Some(original) => transaction.stage(path, original, contents),
+            None => transaction.stage_new(path, contents),
+        }
+    }
+
+    Ok(())
+}
+
+/// The path of the changelog of `package`.
+fn changelog_path(package: &PackageMetadata) -> Result<PathBuf> {
+    let config = PackageConfig::from_package(package)?;
+
+    Ok(package.manifest_path().as_std_path().with_file_name(
+        config
+            .changelog
+            .as_deref()
+            .unwrap_or(changelog::DEFAULT_CHANGELOG),
+    ))
+}
+
+/// Stage the updates for preparing the selected crates of the workspace of `graph`
+/// for a release.
+///
+/// Every manifest and lockfile of the workspace is staged, including those that are
+/// left unchanged.
+fn prepare_transaction(
+    graph: &PackageGraph,
+    next_release_version: &str,
+    selection: &PackageSelection,
+) -> Result<Transaction> {
     let link_map = graph
         .workspace()
         .iter()
@@ -247,22 +763,31 @@ fn internal_prepare(manifest_path: Option<&Path>, next_release_version: String)
         // dependency with an explicit version
         .filter(|link| !link.dev_only() || !link.version_req().comparators.is_empty())
         .filter(|link| link.to().in_workspace())
+        .filter(|link| selection.contains(link.to().name()))
         .map(|link| (link.from().id(), link))
         .into_group_map();
 
+    // Every edit is computed before anything is written so that an error part way
+    // through the workspace leaves all of the files untouched.
+    let mut transaction = Transaction::default();
+
     debug!("Setting version information for packages in the workspace.");
     for package in graph.workspace().iter() {
         let path = package.manifest_path();
         debug!("reading {}", path.as_str());
-        let mut cargo = read_cargo_toml(path.as_std_path())?;
+        let original = read_file(path.as_std_path())?;
+        let mut cargo = parse_cargo_toml(path.as_std_path(), &original)?;
+        let selected = selection.contains(package.name());
 
-        info!(
-            "Setting the version of {} to {}",
-            package.name(),
-            &next_release_version
-        );
-        set_package_version(&mut cargo, &next_release_version)
-            .map_err(|err| err.into_error(path))?;
+        if selected {
+            info!(
+                "Setting the version of {} to {}",
+                package.name(),
+                next_release_version
+            );
+            set_package_version(&mut cargo, next_release_version)
+                .map_err(|err| err.into_error(path))?;
+        }
 
         if let Some(links) = link_map.get(package.id()) {
             for link in links {
@@ -271,11 +796,11 @@ fn internal_prepare(manifest_path: Option<&Path>, next_release_version: String)
                         "Upgrading dependency of {} to {}@{}",
                         link.to().name(),
                         package.name(),
-                        &next_release_version
+                        next_release_version
                     );
                     set_dependencies_version(
                         &mut cargo,
-                        &next_release_version,
+                        next_release_version,
                         DependencyType::Normal,
                         link.to().name(),
                     )
@@ -286,11 +811,11 @@ fn internal_prepare(manifest_path: Option<&Path>, next_release_version: String)
                         "Upgrading build-dependency of {} to {}@{}",
                         link.to().name(),
                         package.name(),
-                        &next_release_version
+                        next_release_version
                     );
                     set_dependencies_version(
                         &mut cargo,
-                        &next_release_version,
+                        next_release_version,
                         DependencyType::Build,
                         link.to().name(),
                     )
@@ -301,11 +826,11 @@ fn internal_prepare(manifest_path: Option<&Path>, next_release_version: String)
                         "Upgrading dev-dependency of {} to {}@{}",
                         link.to().name(),
                         package.name(),
-                        &next_release_version
+                        next_release_version
                     );
                     set_dependencies_version(
                         &mut cargo,
-                        &next_release_version,
+                        next_release_version,
                         DependencyType::Dev,
                         link.to().name(),
                     )
@@ -314,126 +839,1207 @@ fn internal_prepare(manifest_path: Option<&Path>, next_release_version: String)
             }
         }
 
-        debug!("writing {}", path.as_str());
-        write_cargo_toml(path.as_std_path(), cargo)?;
+        transaction.stage(path.as_std_path(), original, cargo.to_string());
+
+        // Update the lockfile metadata.
+        //
+        // This code currently only updates the version number of the crate's
+        // self-describing metadata.
+        //
+        // Unsupported: updating metadata of in-workspace dependencies. I
+        // didn't take a stab at this yet because I don't have this issue
+        // personal yet, and without a repository in which I can reproduce
+        // this problem I think it's most responsible to keep the code simple
+        // and readable.
+        let lockfile_path = get_cargo_lock(path.as_std_path());
+        if lockfile_path.exists() {
+            debug!("reading {}", lockfile_path.to_string_lossy());
+            let original = read_file(&lockfile_path)?;
+            let mut lockfile = parse_cargo_toml(&lockfile_path, &original)?;
+
+            if selected {
+                set_lockfile_self_describing_metadata(
+                    &mut lockfile,
+                    next_release_version,
+                    package.name(),
+                )?;
+            }
+
+            transaction.stage(lockfile_path, original, lockfile.to_string());
+        }
+    }
+
+    Ok(transaction)
+}
+
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `publish` function.
+pub struct PublishArgs {
+    /// Whether the `--no-dirty` flag should be passed to `cargo publish`.
+    pub no_dirty: Option<bool>,
+
+    /// A map of packages and features to pass to `cargo publish`.
+    pub features: Option<HashMap<String, Vec<String>>>,
+
+    /// Optionally passes a `--registry` flag `cargo publish`.
+    pub registry: Option<String>,
+
+    /// The maximum number of crates to publish at the same time (defaults to 1).
+    pub jobs: Option<u32>,
+
+    /// The template for the web url of the published crates, overriding the one in
+    /// the workspace metadata.
+    pub url_template: Option<String>,
+
+    /// Whether to include docs.rs links for crates published to `crates.io`,
+    /// overriding the workspace metadata.
+    pub docs_rs: Option<bool>,
+
+    /// The crate linked in the release record, overriding the one in the workspace
+    /// metadata.
+    pub main_crate: Option<String>,
+
+    /// Whether to resume an interrupted publish from its journal, skipping the crates
+    /// that it already published.
+    pub resume: Option<bool>,
+
+    /// The crates of the workspace to publish (defaults to all of them).
+    pub packages: Option<Vec<String>>,
+
+    /// Whether to create an annotated git tag for each published crate once every
+    /// crate is published.
+    pub tag: Option<bool>,
+
+    /// The format of the tags, such as `{name}-v{version}`, overriding the one in the
+    /// workspace metadata.
+    pub tag_format: Option<String>,
+
+    /// The longest time, in seconds, that `cargo publish` may take for each crate
+    /// before it is killed (defaults to no limit).
+    pub timeout: Option<u32>,
+}
+
+/// Publish the publishable crates from the workspace.
+///
+/// The publishable crates are the crates in the workspace other than those
+/// whose `package.publish` field is set to `false` or that includes a registry other
+/// than `crates.io`. The returned [`PublishReport`] lists the outcome for each crate
+/// in the workspace and doubles as the release record for `semantic-release`. If a
+/// crate fails to publish, or the published crates cannot be tagged, the report is
+/// carried by the [`Error::PublishFailed`] error instead.
+///
+/// A journal of the crates published so far is kept in the target directory of the
+/// workspace until every crate is published. With the `resume` option a publish
+/// that was interrupted continues from the first crate that it did not publish.
+///
+/// This implements the `publish` step for `semantic-release` for a Cargo-based
+/// Rust workspace.
+pub fn publish(manifest_path: Option<&Path>, opts: &PublishArgs) -> Result<PublishReport> {
+    internal_publish(manifest_path, opts, &StepContext::default())
+}
+
+fn internal_publish(
+    manifest_path: Option<&Path>,
+    opts: &PublishArgs,
+    context: &StepContext,
+) -> Result<PublishReport> {
+    debug!("Getting the package graph");
+    let workspace = Workspace::load(manifest_path, context)?;
+
+    publish_workspace(workspace.graph(), workspace.config(), opts, context)
+}
+
+/// Publish the selected crates of the workspace of `graph`, with the release
+/// configuration `config`, running `cargo publish` in the directory and environment
+/// of `context`.
+fn publish_workspace(
+    graph: &PackageGraph,
+    config: &WorkspaceConfig,
+    opts: &PublishArgs,
+    context: &StepContext,
+) -> Result<PublishReport> {
+    let optional_registry = opts.registry.as_deref();
+    let registry = optional_registry.unwrap_or("crates.io");
+    let jobs = opts.jobs.unwrap_or(1).max(1) as usize;
+
+    let links = ReleaseLinks::new(
+        config,
+        optional_registry,
+        opts.url_template.as_deref(),
+        opts.docs_rs,
+    )?;
+    let configured_main_crate = opts
+        .main_crate
+        .as_deref()
+        .or(config.main_crate.as_deref())
+        .map(|name| configured_main_crate(graph, name, optional_registry))
+        .transpose()?;
+
+    let selection = PackageSelection::new(graph, opts.packages.as_deref())?;
+
+    let journal_path = Journal::path(graph);
+    let journal = if opts.resume.unwrap_or_default() {
+        let journal = Journal::resume(journal_path, registry)?;
+        journal.verify(graph)?;
+        journal
+    } else {
+        Journal::new(journal_path, registry)?
+    };
+
+    let mut attempted: Vec<_> = journal
+        .published()
+        .into_iter()
+        .map(|entry| {
+            info!(
+                "Skipping {}@{}, which was published before the publish was interrupted",
+                entry.name, entry.version
+            );
+            CrateReport::new(
+                entry.name,
+                entry.version,
+                registry,
+                PublishStatus::Published,
+            )
+        })
+        .collect();
+    let mut outcome = Ok(());
+    for level in publishable_package_levels(graph, optional_registry) {
+        let commands: Vec<_> = level
+            .iter()
+            .filter(|pkg| selection.contains(pkg.name()))
+            .filter(|pkg| !attempted.iter().any(|report| report.name == pkg.name()))
+            .map(|pkg| PublishCommand::for_package(pkg, opts, context))
+            .collect();
+        outcome = publish_level(&commands, jobs, &journal, &mut attempted);
+        if outcome.is_err() {
+            break;
+        }
+    }
+    if outcome.is_ok() {
+        journal.complete()?;
+    }
+
+    let count = attempted
+        .iter()
+        .filter(|report| report.status == PublishStatus::Published)
+        .count();
+    let last_published = attempted
+        .iter()
+        .rev()
+        .find(|report| report.status == PublishStatus::Published)
+        .map(|report| (report.name.clone(), report.version.clone()));
+
+    let main_crate = match (configured_main_crate, graph.workspace().member_by_path("")) {
+        (Some(pkg), _) => Some((pkg.name().to_owned(), pkg.version().to_string())),
+        (None, Ok(pkg))
+            if package_is_publishable(&pkg, optional_registry)
+                && selection.contains(pkg.name()) =>
+        {
+            Some((pkg.name().to_owned(), pkg.version().to_string()))
+        }
+        _ => last_published,
+    };
+
+    let name = format!("{} packages ({} packages published)", registry, count);
+    let release = match main_crate.as_ref() {
+        Some((main_crate, version)) => {
+            debug!("creating release record with main crate: {}", main_crate);
+            links.release(name, main_crate, version)?
+        }
+        None => {
+            debug!("no main crate for the release record");
+            Release::new::<&str>(name, None, "")?
+        }
+    };
+
+    let mut attempted: HashMap<_, _> = attempted
+        .into_iter()
+        .map(|report| (report.name.clone(), report))
+        .collect();
+    let crates = graph
+        .query_workspace()
+        .resolve_with_fn(|_, link| !link.dev_only())
+        .packages(DependencyDirection::Reverse)
+        .filter(|pkg| pkg.in_workspace())
+        .map(|pkg| match attempted.remove(pkg.name()) {
+            Some(report) if report.status == PublishStatus::Published => {
+                let url = links.crate_url(&report.name, &report.version)?;
+                let docs_url = links.docs_url(&report.name, &report.version);
+                Ok(report.url(url).docs_url(docs_url))
+            }
+            Some(report) => Ok(report),
+            None => Ok(CrateReport::new(
+                pkg.name(),
+                pkg.version().to_string(),
+                registry,
+                PublishStatus::Skipped,
+            )),
+        })
+        .collect::<Result<_>>()?;
+
+    let report = PublishReport {
+        name: release.name,
+        url: release.url.map(String::from),
+        docs_url: main_crate
+            .as_ref()
+            .and_then(|(main_crate, version)| links.docs_url(main_crate, version)),
+        registry: registry.to_owned(),
+        crates,
+    };
+
+    let report_json = serde_json::to_string(&report).map_err(|err| {
+        let main_crate = main_crate.as_ref().map(|(name, _)| name.as_str());
+        Error::write_release_error(err, main_crate.unwrap_or(registry))
+    })?;
+    info!("{}", report_json);
+    if let Err(err) = outcome {
+        return Err(Error::publish_failed(report, err));
+    }
+
+    if opts.tag.unwrap_or_default() {
+        let tag_format = release_tag_format(config, opts);
+        let root = graph.workspace().root().as_std_path();
+        if let Err(err) = tag_published(root, &report, tag_format) {
+            return Err(Error::publish_failed(report, err));
+        }
+    }
+
+    Ok(report)
+}
+
+/// The format of the tags of the crates published with `opts`.
+fn release_tag_format<'a>(config: &'a WorkspaceConfig, opts: &'a PublishArgs) -> &'a str {
+    opts.tag_format
+        .as_deref()
+        .or(config.tag_format.as_deref())
+        .unwrap_or(tags::DEFAULT_TAG_FORMAT)
+}
+
+/// Create an annotated git tag, named by `tag_format`, for each crate of `report`
+/// that was published.
+fn tag_published(root: &Path, report: &PublishReport, tag_format: &str) -> Result<()> {
+    let published: Vec<_> = report
+        .published()
+        .map(|report| (report.name.clone(), report.version.clone()))
+        .collect();
+
+    tags::create_tags(root, &published, tag_format)?;
+
+    Ok(())
+}
+
+/// Report what a failed release left published.
+///
+/// The journal of an interrupted publish, which is kept in the target directory of the
+/// workspace, records the crates published before the failure. Those crates are
+/// logged with a hint to resume the publish or to yank them, and the returned
+/// [`FailReport`] is written as `rollback.json` next to the journal, where it is kept
+/// after the release has ended.
+///
+/// This implements the `fail` step for `semantic-release` for a Cargo-based Rust
+/// workspace.
+pub fn fail(manifest_path: Option<&Path>) -> Result<FailReport> {
+    internal_fail(manifest_path, &StepContext::default())
+}
+
+fn internal_fail(manifest_path: Option<&Path>, context: &StepContext) -> Result<FailReport> {
+    debug!("Building package graph");
+    let graph = get_package_graph_in(manifest_path, context)?;
+    let path = Journal::path(&graph);
+
+    let journal = Journal::read(&path)?;
+    let published: Vec<_> = journal
+        .as_ref()
+        .map(Journal::published)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| PublishedCrate {
+            name: entry.name,
+            version: entry.version,
+        })
+        .collect();
+    let registry = journal.as_ref().map(Journal::registry);
+    let rollback = Journal::rollback_path(&graph);
+    if published.is_empty() {
+        let hint = "No crates were published, so there is nothing to roll back.".to_owned();
+        info!("{}", hint);
+        return write_fail_report(
+            &rollback,
+            FailReport {
+                journal: journal.map(|_| path.display().to_string()),
+                rollback: rollback.display().to_string(),
+                registry,
+                published,
+                hint,
+            },
+        );
+    }
+
+    warn!(
+        "The release failed after publishing {} to {}:",
+        if published.len() == 1 {
+            "1 crate".to_owned()
+        } else {
+            format!("{} crates", published.len())
+        },
+        registry.as_deref().unwrap_or("crates.io")
+    );
+    for entry in &published {
+        warn!("  {} {}", entry.name, entry.version);
+    }
+
+    let registry_flag = registry
+        .as_deref()
+        .filter(|registry| *registry != "crates.io")
+        .map(|registry| format!(" --registry {}", registry))
+        .unwrap_or_default();
+    let yank: Vec<_> = published
+        .iter()
+        .map(|entry| entry.version.as_str())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|version| format!("`semantic-release-cargo yank{} {}`", registry_flag, version))
+        .collect();
+    let hint = format!(
+        "Run `semantic-release-cargo publish --resume{}` to publish the remaining crates, or {} to yank the published ones.",
+        registry_flag,
+        yank.join(" and ")
+    );
+    warn!("{}", hint);
+
+    write_fail_report(
+        &rollback,
+        FailReport {
+            journal: Some(path.display().to_string()),
+            rollback: rollback.display().to_string(),
+            registry,
+            published,
+            hint,
+        },
+    )
+}
+
+/// Write `report` as JSON to `path`, where it outlasts the run of the `fail` step.
+fn write_fail_report(path: &Path, report: FailReport) -> Result<FailReport> {
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|err| Error::journal_error(err.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| Error::file_write_error(err, dir))?;
+    }
+    transaction::write_atomic(path, &json).map_err(|err| Error::file_write_error(err, path))?;
+    info!(
+        "Wrote the report of the failed release to {}",
+        path.display()
+    );
+
+    Ok(report)
+}
+
+/// Plan a release of the workspace without changing anything.
+///
+/// The returned [`ReleasePlan`] records the edits to the manifests, lockfiles and
+/// changelogs that [`prepare_with_args`] would make for `next_release_version` with
+/// `prepare_opts`, and the `cargo publish` commands and tags that [`publish`] would
+/// then run and create with `opts`, along with a fingerprint of the workspace. It can
+/// be serialized to JSON, reviewed, and later run with [`apply`]. The `commit` and
+/// `resume` options are not used.
+pub fn plan(
+    manifest_path: Option<&Path>,
+    next_release_version: &str,
+    prepare_opts: &PrepareArgs,
+    opts: &PublishArgs,
+) -> Result<ReleasePlan> {
+    internal_plan(
+        manifest_path,
+        next_release_version,
+        prepare_opts,
+        opts,
+        &StepContext::default(),
+    )
+}
+
+/// Apply a release plan made by [`plan`].
+///
+/// Applying the plan writes its edits and then runs its `cargo publish` commands
+/// level by level, as [`publish`] does, with up to `jobs` of them at a time and a
+/// journal of the published crates. Once a crate fails no further crates are
+/// published and the error is an [`Error::PublishFailed`] with the report of the
+/// crates; otherwise the published crates are tagged if the plan says so, and a
+/// failure to tag them is also an [`Error::PublishFailed`]. Nothing is done if the
+/// manifests, lockfiles, changelogs or cargo configuration of the workspace have
+/// changed since the plan was made.
+pub fn apply(manifest_path: Option<&Path>, plan: &ReleasePlan) -> Result<PublishReport> {
+    internal_apply(manifest_path, plan, &StepContext::default())
+}
+
+fn internal_plan(
+    manifest_path: Option<&Path>,
+    next_release_version: &str,
+    prepare_opts: &PrepareArgs,
+    opts: &PublishArgs,
+    context: &StepContext,
+) -> Result<ReleasePlan> {
+    debug!("Building package graph");
+    let workspace = Workspace::load(manifest_path, context)?;
+
+    plan_workspace(
+        workspace.graph(),
+        workspace.config(),
+        next_release_version,
+        prepare_opts,
+        opts,
+    )
+}
+
+/// Plan a release of `next_release_version` of the workspace of `graph`, with the
+/// release configuration `config`.
+fn plan_workspace(
+    graph: &PackageGraph,
+    config: &WorkspaceConfig,
+    next_release_version: &str,
+    prepare_opts: &PrepareArgs,
+    opts: &PublishArgs,
+) -> Result<ReleasePlan> {
+    let root = graph.workspace().root().as_std_path();
+    let optional_registry = opts.registry.as_deref();
+    let registry = optional_registry.unwrap_or("crates.io");
+
+    let links = ReleaseLinks::new(
+        config,
+        optional_registry,
+        opts.url_template.as_deref(),
+        opts.docs_rs,
+    )?;
+    let configured_main_crate = opts
+        .main_crate
+        .as_deref()
+        .or(config.main_crate.as_deref())
+        .map(|name| configured_main_crate(graph, name, optional_registry))
+        .transpose()?;
+
+    let prepared = PackageSelection::new(graph, prepare_opts.packages.as_deref())?;
+    let selection = PackageSelection::new(graph, opts.packages.as_deref())?;
+    let mut transaction = prepare_transaction(graph, next_release_version, &prepared)?;
+    let fingerprint = workspace_fingerprint(graph, &transaction)?;
+    if prepare_opts.changelog.unwrap_or_default() {
+        stage_changelogs(
+            graph,
+            next_release_version,
+            &prepared,
+            prepare_opts,
+            &mut transaction,
+        )?;
+    }
+
+    let crates = graph
+        .query_workspace()
+        .resolve_with_fn(|_, link| !link.dev_only())
+        .packages(DependencyDirection::Reverse)
+        .filter(|pkg| pkg.in_workspace())
+        .map(|pkg| PlannedCrate {
+            name: pkg.name().to_owned(),
+            version: pkg.version().to_string(),
+            next_version: if prepared.contains(pkg.name()) {
+                next_release_version.to_owned()
+            } else {
+                pkg.version().to_string()
+            },
+            publish: selection.contains(pkg.name())
+                && package_is_publishable(&pkg, optional_registry),
+        })
+        .collect();
+
+    let levels: Vec<Vec<_>> = publishable_package_levels(graph, optional_registry)
+        .into_iter()
+        .map(|level| {
+            level
+                .into_iter()
+                .filter(|pkg| selection.contains(pkg.name()))
+                .collect()
+        })
+        .filter(|level: &Vec<_>| !level.is_empty())
+        .collect();
+    let publish_order = levels
+        .iter()
+        .map(|level| level.iter().map(|pkg| pkg.name().to_owned()).collect())
+        .collect();
+    let commands = levels
+        .iter()
+        .flatten()
+        .map(|pkg| {
+            let manifest_path = relative_path(root, pkg.manifest_path().as_std_path());
+            Ok(PlannedPublish {
+                name: pkg.name().to_owned(),
+                version: next_release_version.to_owned(),
+                args: publish_command_args(&manifest_path, pkg.name(), opts),
+                manifest_path,
+                url: links.crate_url(pkg.name(), next_release_version)?,
+                docs_url: links.docs_url(pkg.name(), next_release_version),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let main_crate = match (configured_main_crate, graph.workspace().member_by_path("")) {
+        (Some(pkg), _) => Some(pkg.name().to_owned()),
+        (None, Ok(pkg))
+            if package_is_publishable(&pkg, optional_registry)
+                && selection.contains(pkg.name()) =>
+        {
+            Some(pkg.name().to_owned())
+        }
+        _ => commands.last().map(|command| command.name.clone()),
+    };
+
+    let edits = transaction
+        .edits()
+        .filter(|(_, original, contents)| original != contents)
+        .map(|(path, _, contents)| PlannedEdit {
+            path: relative_path(root, path),
+            kind: match path.file_name().and_then(|name| name.to_str()) {
+                Some("Cargo.lock") => EditKind::Lockfile,
+                Some("Cargo.toml") => EditKind::Manifest,
+                _ => EditKind::Changelog,
+            },
+            contents: contents.to_owned(),
+        })
+        .collect();
+
+    Ok(ReleasePlan {
+        next_version: next_release_version.to_owned(),
+        registry: registry.to_owned(),
+        fingerprint,
+        crates,
+        publish_order,
+        edits,
+        diff: transaction.diff(root),
+        commands,
+        main_crate,
+        timeout: opts.timeout,
+        jobs: opts.jobs,
+        tag_format: opts
+            .tag
+            .unwrap_or_default()
+            .then(|| release_tag_format(config, opts).to_owned()),
+    })
+}
+
+fn internal_apply(
+    manifest_path: Option<&Path>,
+    plan: &ReleasePlan,
+    context: &StepContext,
+) -> Result<PublishReport> {
+    debug!("Building package graph");
+    let graph = get_package_graph_in(manifest_path, context)?;
+
+    apply_plan(&graph, plan, context)
+}
+
+/// Apply `plan` to the workspace of `graph`, running `cargo publish` with the runner of
+/// `context`.
+fn apply_plan(
+    graph: &PackageGraph,
+    plan: &ReleasePlan,
+    context: &StepContext,
+) -> Result<PublishReport> {
+    let root = graph.workspace().root().as_std_path();
+
+    let current = prepare_transaction(graph, &plan.next_version, &PackageSelection::all())?;
+    let fingerprint = workspace_fingerprint(graph, &current)?;
+    if fingerprint != plan.fingerprint {
+        return Err(Error::plan_mismatch(format!(
+            "the files of the workspace have changed since the plan was made (fingerprint {} instead of {})",
+            fingerprint, plan.fingerprint
+        )));
+    }
+
+    let originals: HashMap<_, _> = current
+        .edits()
+        .map(|(path, original, _)| (relative_path(root, path), original))
+        .collect();
+    let changelogs = graph
+        .workspace()
+        .iter()
+        .map(|package| Ok(relative_path(root, &changelog_path(&package)?)))
+        .collect::<Result<HashSet<_>>>()?;
+    let mut transaction = Transaction::default();
+    for edit in &plan.edits {
+        let path = root.join(&edit.path);
+        match (edit.kind, originals.get(&edit.path)) {
+            (EditKind::Manifest | EditKind::Lockfile, Some(original)) => {
+                transaction.stage(path, (*original).to_owned(), edit.contents.as_str())
+            }
+            (EditKind::Changelog, None) if changelogs.contains(&edit.path) => {
+                match fs::read_to_string(&path) {
+                    Ok(original) => transaction.stage(path, original, edit.contents.as_str()),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        transaction.stage_new(path, edit.contents.as_str())
+                    }
+                    Err(err) => return Err(Error::file_read_error(err, &path)),
+                }
+            }
+            _ => {
+                return Err(Error::plan_mismatch(format!(
+                    "{} is not a {} of the workspace",
+                    edit.path,
+                    match edit.kind {
+                        EditKind::Manifest => "manifest",
+                        EditKind::Lockfile => "lockfile",
+                        EditKind::Changelog => "changelog",
+                    }
+                )))
+            }
+        }
+    }
+
+    let planned: HashMap<_, _> = plan
+        .commands
+        .iter()
+        .map(|command| (command.name.as_str(), command))
+        .collect();
+    let manifest_paths: HashMap<_, _> = plan
+        .commands
+        .iter()
+        .map(|command| (command.name.as_str(), root.join(&command.manifest_path)))
+        .collect();
+    let timeout = plan.timeout.map(|secs| Duration::from_secs(secs.into()));
+    let levels = plan
+        .publish_order
+        .iter()
+        .map(|level| {
+            level
+                .iter()
+                .map(|name| {
+                    let command = planned.get(name.as_str()).ok_or_else(|| {
+                        Error::plan_mismatch(format!("there is no command to publish {}", name))
+                    })?;
+                    Ok(PublishCommand {
+                        name: &command.name,
+                        version: command.version.clone(),
+                        registry: &plan.registry,
+                        manifest_path: &manifest_paths[command.name.as_str()],
+                        args: command.args.clone(),
+                        current_dir: Some(root),
+                        timeout,
+                        context,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    debug!("Writing the updated manifests, lockfiles and changelogs.");
+    transaction.commit()?;
+
+    let jobs = plan.jobs.unwrap_or(1).max(1) as usize;
+    let journal = Journal::new(Journal::path(graph), &plan.registry)?;
+    let mut attempted = Vec::new();
+    let mut outcome = Ok(());
+    for commands in &levels {
+        outcome = publish_level(commands, jobs, &journal, &mut attempted);
+        if outcome.is_err() {
+            break;
+        }
+    }
+    if outcome.is_ok() {
+        journal.complete()?;
+    }
+
+    let mut attempted: HashMap<_, _> = attempted
+        .into_iter()
+        .map(|report| (report.name.clone(), report))
+        .collect();
+    let crates: Vec<_> = plan
+        .crates
+        .iter()
+        .map(|crate_plan| match attempted.remove(&crate_plan.name) {
+            Some(report) if report.status == PublishStatus::Published => {
+                let command = planned[crate_plan.name.as_str()];
+                report
+                    .url(command.url.clone())
+                    .docs_url(command.docs_url.clone())
+            }
+            Some(report) => report,
+            None => CrateReport::new(
+                crate_plan.name.as_str(),
+                crate_plan.next_version.as_str(),
+                plan.registry.as_str(),
+                PublishStatus::Skipped,
+            ),
+        })
+        .collect();
+
+    let count = crates
+        .iter()
+        .filter(|report| report.status == PublishStatus::Published)
+        .count();
+    let main_crate = plan
+        .main_crate
+        .as_deref()
+        .and_then(|name| planned.get(name));
+    let report = PublishReport {
+        name: format!("{} packages ({} packages published)", plan.registry, count),
+        url: main_crate.and_then(|command| command.url.clone()),
+        docs_url: main_crate.and_then(|command| command.docs_url.clone()),
+        registry: plan.registry.clone(),
+        crates,
+    };
+
+    let report_json = serde_json::to_string(&report).map_err(|err| {
+        Error::write_release_error(err, plan.main_crate.as_deref().unwrap_or(&plan.registry))
+    })?;
+    info!("{}", report_json);
+    if let Err(err) = outcome {
+        return Err(Error::publish_failed(report, err));
+    }
+
+    if let Some(tag_format) = plan.tag_format.as_deref() {
+        if let Err(err) = tag_published(root, &report, tag_format) {
+            return Err(Error::publish_failed(report, err));
+        }
+    }
+
+    Ok(report)
+}
+
+/// The fingerprint of the workspace of `graph` before the edits in `transaction`.
+///
+/// Besides the files that `transaction` edits, this covers the changelogs of the
+/// crates, which are published with them, and the cargo configuration of the
+/// workspace, which decides where and how they are published.
+fn workspace_fingerprint(graph: &PackageGraph, transaction: &Transaction) -> Result<String> {
+    let root = graph.workspace().root().as_std_path();
+    let mut files: Vec<_> = transaction
+        .edits()
+        .map(|(path, original, _)| (relative_path(root, path), original.to_owned()))
+        .collect();
+
+    let mut inputs = vec![
+        root.join(".cargo").join("config.toml"),
+        root.join(".cargo").join("config"),
+    ];
+    for package in graph.workspace().iter() {
+        inputs.push(changelog_path(&package)?);
+    }
+    for path in inputs {
+        match fs::read_to_string(&path) {
+            Ok(contents) => files.push((relative_path(root, &path), contents)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(Error::file_read_error(err, &path)),
+        }
+    }
+
+    Ok(plan::fingerprint(files.iter().map(|(path, contents)| {
+        (path.as_str(), contents.as_str())
+    })))
+}
+
+/// The path of `path` relative to `root`, using `/` as the separator.
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `tag` function.
+pub struct TagArgs {
+    /// The registry to which the crates are published (defaults to crates.io).
+    pub registry: Option<String>,
+
+    /// The format of the tags, such as `{name}-v{version}`, overriding the one in the
+    /// workspace metadata.
+    pub tag_format: Option<String>,
+
+    /// The crates of the workspace to tag (defaults to all of the publishable
+    /// crates).
+    pub packages: Option<Vec<String>>,
+}
+
+/// Create an annotated git tag for each publishable crate of the workspace.
+///
+/// Each tag points at `HEAD` and is named by the tag format (by default
+/// `{name}-v{version}`) with the name and current version of the crate. Tags that
+/// already point at `HEAD` are kept, and nothing is tagged if any tag already points
+/// at another commit.
+pub fn tag(manifest_path: Option<&Path>, opts: &TagArgs) -> Result<TagReport> {
+    internal_tag(manifest_path, opts, &StepContext::default())
+}
+
+fn internal_tag(
+    manifest_path: Option<&Path>,
+    opts: &TagArgs,
+    context: &StepContext,
+) -> Result<TagReport> {
+    debug!("Building package graph");
+    let workspace = Workspace::load(manifest_path, context)?;
+
+    tag_workspace(workspace.graph(), workspace.config(), opts)
+}
+
+/// Tag the selected publishable crates of the workspace of `graph`, with the release
+/// configuration `config`, at their current versions.
+fn tag_workspace(
+    graph: &PackageGraph,
+    config: &WorkspaceConfig,
+    opts: &TagArgs,
+) -> Result<TagReport> {
+    let selection = PackageSelection::new(graph, opts.packages.as_deref())?;
+    let tag_format = opts
+        .tag_format
+        .as_deref()
+        .or(config.tag_format.as_deref())
+        .unwrap_or(tags::DEFAULT_TAG_FORMAT);
+
+    let crates: Vec<_> = publishable_packages(graph, opts.registry.as_deref())
+        .into_iter()
+        .filter(|pkg| selection.contains(pkg.name()))
+        .map(|pkg| (pkg.name().to_owned(), pkg.version().to_string()))
+        .collect();
+
+    tags::create_tags(graph.workspace().root().as_std_path(), &crates, tag_format)
+}
+
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `yank` function.
+pub struct YankArgs {
+    /// Optionally passes a `--registry` flag to `cargo yank`.
+    pub registry: Option<String>,
+
+    /// Whether to un-yank the version instead of yanking it.
+    pub undo: Option<bool>,
+}
+
+/// Yank a version of the publishable crates of the workspace.
+///
+/// This rolls back a release that went wrong after some of its crates were
+/// published. The publishable crates are yanked in the reverse of the order in
+/// which they are published, and crates that were never published at `version` are
+/// reported as such. With the `undo` option the version is un-yanked instead. If a
+/// crate fails to be yanked, the report is carried by the [`Error::YankFailed`] error.
+pub fn yank(manifest_path: Option<&Path>, version: &str, opts: &YankArgs) -> Result<YankReport> {
+    internal_yank(manifest_path, version, opts, &StepContext::default())
+}
+
+fn internal_yank(
+    manifest_path: Option<&Path>,
+    version: &str,
+    opts: &YankArgs,
+    context: &StepContext,
+) -> Result<YankReport> {
+    debug!("Getting the package graph");
+    let workspace = Workspace::load(manifest_path, context)?;
+
+    yank_workspace(workspace.graph(), version, opts, context)
+}
+
+/// Yank `version` of the publishable crates of the workspace of `graph`, running
+/// `cargo yank` with the runner of `context`.
+fn yank_workspace(
+    graph: &PackageGraph,
+    version: &str,
+    opts: &YankArgs,
+    context: &StepContext,
+) -> Result<YankReport> {
+    let optional_registry = opts.registry.as_deref();
+    let undo = opts.undo.unwrap_or_default();
+
+    let mut crates = Vec::new();
+    let mut outcome = Ok(());
+    for pkg in publishable_package_levels(graph, optional_registry)
+        .iter()
+        .flatten()
+        .rev()
+    {
+        let (report, result) = yank_package(
+            pkg.name(),
+            version,
+            opts,
+            graph.workspace().root().as_std_path(),
+            context,
+        );
+        crates.push(report);
+        if let Err(err) = result {
+            // Keep going so that as much of the release as possible is rolled back.
+            if outcome.is_ok() {
+                outcome = Err(err);
+            }
+        }
+    }
+
+    let report = YankReport {
+        version: version.to_owned(),
+        registry: optional_registry.unwrap_or("crates.io").to_owned(),
+        undo,
+        crates,
+    };
+    if let Ok(report_json) = serde_json::to_string(&report) {
+        info!("{}", report_json);
+    }
+
+    match outcome {
+        Ok(()) => Ok(report),
+        Err(err) => Err(Error::yank_failed(report, err)),
+    }
+}
+
+/// Run `cargo yank` for a version of a single crate.
+fn yank_package(
+    name: &str,
+    version: &str,
+    opts: &YankArgs,
+    workspace_root: &Path,
+    context: &StepContext,
+) -> (YankedCrate, Result<()>) {
+    let undo = opts.undo.unwrap_or_default();
+    info!(
+        "{} version {} of {} on {} registry",
+        if undo { "Un-yanking" } else { "Yanking" },
+        version,
+        name,
+        opts.registry.as_deref().unwrap_or("crates.io")
+    );
 
-        // Update the lockfile metadata.
-        //
-        // This code currently only updates the version number of the crate's
-        // self-describing metadata.
-        //
-        // Unsupported: updating metadata of in-workspace dependencies. I
-        // didn't take a stab at this yet because I don't have this issue
-        // personal yet, and without a repository in which I can reproduce
-        // this problem I think it's most responsible to keep the code simple
-        // and readable.
-        let lockfile_path = get_cargo_lock(path.as_std_path());
-        if lockfile_path.exists() {
-            debug!("reading {}", lockfile_path.to_string_lossy());
-            let mut lockfile = read_cargo_toml(&lockfile_path)?;
+    let mut args = vec![
+        "yank".to_owned(),
+        "--version".to_owned(),
+        version.to_owned(),
+    ];
+    if undo {
+        args.push("--undo".to_owned());
+    }
+    if let Some(registry) = opts.registry.as_ref() {
+        args.push("--registry".to_owned());
+        args.push(registry.clone());
+    }
+    args.push(name.to_owned());
+    let invocation = context.invocation(Some(name), args, Some(workspace_root));
 
-            set_lockfile_self_describing_metadata(
-                &mut lockfile,
-                &next_release_version,
-                package.name(),
-            )?;
+    trace!("running: {:?}", invocation);
 
-            debug!("writing {}", lockfile_path.to_string_lossy());
-            write_cargo_toml(&lockfile_path, lockfile)?;
+    let output = match context.runner().yank(&invocation) {
+        Ok(output) => output,
+        Err(err) => {
+            let report = YankedCrate::new(name, YankStatus::Failed, &[]);
+            return (report, Err(Error::cargo_yank(err, name)));
         }
+    };
+
+    trace!("cargo yank stderr");
+    trace!("-----------------");
+    log_bytes(Level::Trace, "", &output.stderr);
+
+    if output.status.success() {
+        let status = if undo {
+            YankStatus::Unyanked
+        } else {
+            YankStatus::Yanked
+        };
+        (YankedCrate::new(name, status, &output.stderr), Ok(()))
+    } else if is_unpublished_version(&output.stderr) {
+        info!("{} was not published at version {}", name, version);
+        let report = YankedCrate::new(name, YankStatus::Unpublished, &output.stderr);
+        (report, Ok(()))
+    } else {
+        error!(
+            "yanking package {} failed: {}\n{}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let err = Error::cargo_yank_status(output.status, name, &output.stderr);
+        (
+            YankedCrate::new(name, YankStatus::Failed, &output.stderr),
+            Err(err),
+        )
     }
+}
 
-    Ok(())
+/// Whether the stderr output of a failed `cargo yank` says that the version was never
+/// published.
+fn is_unpublished_version(stderr: &[u8]) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    stderr.contains("does not have a version") || stderr.contains("does not exist")
 }
 
 #[cfg_attr(feature = "napi-rs", napi(object))]
 #[derive(Debug, Default)]
-/// Arguments to be passed to the `publish` function.
-pub struct PublishArgs {
-    /// Whether the `--no-dirty` flag should be passed to `cargo publish`.
-    pub no_dirty: Option<bool>,
-
-    /// A map of packages and features to pass to `cargo publish`.
-    pub features: Option<HashMap<String, Vec<String>>>,
-
-    /// Optionally passes a `--registry` flag `cargo publish`.
-    pub registry: Option<String>,
+/// Arguments to be passed to the `next_version` function.
+pub struct NextVersionArgs {
+    /// The prefix of the release tags, followed by the version (defaults to `v`).
+    pub tag_prefix: Option<String>,
 }
 
-/// Publish the publishable crates from the workspace.
+/// Find the next release version from the conventional commits since the last
+/// release.
 ///
-/// The publishable crates are the crates in the workspace other than those
-/// whose `package.publish` field is set to `false` or that includes a registry other
-/// than `crates.io`.
+/// The last release is the highest version among the release tags reachable from
+/// `HEAD` of the git repository at `repository` (or the current directory). A
+/// breaking change calls for a major release (a minor release while the major
+/// version is 0), a `feat` for a minor release and a `fix` or `perf` for a patch
+/// release. Without a previous release the next version is 1.0.0. `None` is returned
+/// if no commit calls for a release.
 ///
-/// This implements the `publish` step for `semantic-release` for a Cargo-based
-/// Rust workspace.
-#[cfg(feature = "napi-rs")]
-#[napi]
-pub fn publish(opts: Option<PublishArgs>) -> Result<()> {
-    let manifest_path: Option<&Path> = None;
-    internal_publish(manifest_path, &opts.unwrap_or_default())
+/// This gives the version that the `analyzeCommits` step of `semantic-release` would
+/// choose, for use without `semantic-release`.
+pub fn next_version(repository: Option<&Path>, opts: &NextVersionArgs) -> Result<Option<String>> {
+    internal_next_version(repository, opts)
 }
 
-/// Publish the publishable crates from the workspace.
+fn internal_next_version(
+    repository: Option<&Path>,
+    opts: &NextVersionArgs,
+) -> Result<Option<String>> {
+    let repository = repository.unwrap_or_else(|| Path::new("."));
+    let tag_prefix = opts.tag_prefix.as_deref().unwrap_or("v");
+
+    let version = commits::next_version(repository, tag_prefix)?;
+    match &version {
+        Some(version) => info!("The next release version is {}", version),
+        None => info!("There are no changes that call for a release"),
+    }
+
+    Ok(version.map(|version| version.to_string()))
+}
+
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `generate_notes` function.
+pub struct GenerateNotesArgs {
+    /// The registry to which the crates are published (defaults to crates.io).
+    pub registry: Option<String>,
+
+    /// The template for the web url of a published crate, such as
+    /// `https://crates.internal/crates/{name}/{version}`. This overrides the
+    /// template configured for the registry in the workspace metadata.
+    pub url_template: Option<String>,
+
+    /// Whether to include docs.rs links for crates published to crates.io.
+    pub docs_rs: Option<bool>,
+}
+
+/// Generate release notes for the commits in the git revision range `range`, with a
+/// section for each crate of the workspace.
 ///
-/// The publishable crates are the crates in the workspace other than those
-/// whose `package.publish` field is set to `false` or that includes a registry other
-/// than `crates.io`.
+/// A commit belongs to the crates whose directories contain the files it touched and
+/// to the crate named by its conventional commit scope. Each crate with a breaking
+/// change, feature, bug fix or performance improvement gets a Markdown section headed
+/// by its name and `next_release_version`, linked to the published crate when its
+/// url is known.
 ///
-/// This implements the `publish` step for `semantic-release` for a Cargo-based
-/// Rust workspace.
-#[cfg(not(feature = "napi-rs"))]
-pub fn publish(manifest_path: Option<&Path>, opts: &PublishArgs) -> Result<()> {
-    internal_publish(manifest_path, opts)
+/// This implements the `generateNotes` step for `semantic-release` for a Cargo-based
+/// Rust workspace. If `manifest_path` is provided then it is expected to give the
+/// path to the `Cargo.toml` file for the root of the workspace. If `manifest_path` is
+/// `None` then `generate_notes` will look for the root of the workspace in a
+/// `Cargo.toml` file in the current directory.
+pub fn generate_notes(
+    manifest_path: Option<&Path>,
+    range: &str,
+    next_release_version: &str,
+    opts: &GenerateNotesArgs,
+) -> Result<String> {
+    internal_generate_notes(
+        manifest_path,
+        range,
+        next_release_version,
+        opts,
+        &StepContext::default(),
+    )
 }
 
-fn internal_publish(manifest_path: Option<&Path>, opts: &PublishArgs) -> Result<()> {
-    debug!("Getting the package graph");
-    let graph = get_package_graph(manifest_path)?;
+fn internal_generate_notes(
+    manifest_path: Option<&Path>,
+    range: &str,
+    next_release_version: &str,
+    opts: &GenerateNotesArgs,
+    context: &StepContext,
+) -> Result<String> {
+    let graph = get_package_graph_in(manifest_path, context)?;
     let optional_registry = opts.registry.as_deref();
 
-    let mut count = 0;
-    let mut last_id = None;
+    let config = WorkspaceConfig::from_graph(&graph)?;
+    let links = ReleaseLinks::new(
+        &config,
+        optional_registry,
+        opts.url_template.as_deref(),
+        opts.docs_rs,
+    )?;
 
-    process_publishable_packages(&graph, optional_registry, |pkg| {
-        count += 1;
-        last_id = Some(pkg.id().clone());
-        publish_package(pkg, opts)
-    })?;
+    let releases = graph
+        .query_workspace()
+        .resolve_with_fn(|_, link| !link.dev_only())
+        .packages(DependencyDirection::Reverse)
+        .filter(|pkg| pkg.in_workspace())
+        .map(|pkg| {
+            let name = pkg.name();
+            let publishable = package_is_publishable(&pkg, optional_registry);
+            Ok(notes::CrateRelease {
+                name: name.to_owned(),
+                version: next_release_version.to_owned(),
+                url: if publishable {
+                    links.crate_url(name, next_release_version)?
+                } else {
+                    None
+                },
+                docs_url: publishable
+                    .then(|| links.docs_url(name, next_release_version))
+                    .flatten(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    let main_crate = match graph.workspace().member_by_path("") {
-        Ok(pkg) if package_is_publishable(&pkg, optional_registry) => Some(pkg.name()),
-        _ => last_id.map(|id| {
-            graph
-                .metadata(&id)
-                .expect("id of a processed package not found in the package graph")
-                .name()
-        }),
-    };
+    notes::release_notes(&graph, range, &releases)
+}
 
-    if let Some(main_crate) = main_crate {
-        debug!("printing release record with main crate: {}", main_crate);
-        let name = format!(
-            "{} packages ({} packages published)",
-            optional_registry.unwrap_or("crates.io"),
-            count
-        );
+/// Find the crates of the workspace affected by the changes since the git ref `base`.
+///
+/// A crate is affected if a file in its directory changed since `base` (including
+/// uncommitted changes), or if it depends on an affected crate. The names of the
+/// affected crates can be used as the `packages` of [`PrepareArgs`] and
+/// [`PublishArgs`] to release only those crates.
+///
+/// If `manifest_path` is provided then it is expected to give the path to the
+/// `Cargo.toml` file for the root of the workspace. If `manifest_path` is `None` then
+/// `affected` will look for the root of the workspace in a `Cargo.toml` file in the
+/// current directory.
+pub fn affected(manifest_path: Option<&Path>, base: &str) -> Result<AffectedPackages> {
+    internal_affected(manifest_path, base, &StepContext::default())
+}
 
-        // format the release metadata for writing to json
-        let release_meta_json = if optional_registry.is_none() {
-            serde_json::to_string(&Release::new_crates_io_release(name, main_crate)?)
-        } else {
-            serde_json::to_string(&Release::new::<&str>(name, None, main_crate)?)
-        }
-        .map_err(|err| Error::write_release_error(err, main_crate))?;
+fn internal_affected(
+    manifest_path: Option<&Path>,
+    base: &str,
+    context: &StepContext,
+) -> Result<AffectedPackages> {
+    debug!("Building package graph");
+    let workspace = Workspace::load(manifest_path, context)?;
 
-        info!("{:?}", release_meta_json);
-    } else {
-        debug!("no release record to print");
-    }
+    affected_workspace(workspace.graph(), base)
+}
 
-    Ok(())
+/// The crates of the workspace of `graph` affected by the changes since `base`.
+fn affected_workspace(graph: &PackageGraph, base: &str) -> Result<AffectedPackages> {
+    let affected = affected::affected_packages(graph, base)?;
+    info!(
+        "The crates affected by the changes since {} are: {}",
+        base,
+        affected.affected.join(", ")
+    );
+
+    Ok(affected)
+}
+
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Default)]
+/// Arguments to be passed to the `list_packages` function.
+pub struct ListPackagesArgs {
+    /// The alternate registry to which the crates would be published.
+    pub registry: Option<String>,
 }
 
 /// List the packages from the workspace in the order of their dependencies.
@@ -466,30 +2072,135 @@ pub fn list_packages_with_arguments(
     internal_list_packages(alternate_registry, manifest_path)
 }
 
+/// List the crates of the workspace in the order in which they are published.
+///
+/// Each crate is listed with its version, its manifest, whether it would be published
+/// to the registry of `opts` (and if not, why not) and the workspace crates that it
+/// depends on.
+pub fn package_list(
+    manifest_path: Option<impl AsRef<Path>>,
+    opts: &ListPackagesArgs,
+) -> Result<Vec<PackageInfo>> {
+    internal_package_list(
+        manifest_path,
+        opts.registry.as_deref(),
+        &StepContext::default(),
+    )
+}
+
 fn internal_list_packages(
     alternate_registry: Option<&str>,
     manifest_path: Option<impl AsRef<Path>>,
 ) -> Result<()> {
+    info!("iterating the workspace crates in dependency order");
+    for pkg in internal_package_list(manifest_path, alternate_registry, &StepContext::default())? {
+        match pkg.reason {
+            None => error!("{}({})", pkg.name, pkg.version),
+            Some(reason) => debug!(
+                "{}({}) is not publishable: {}",
+                pkg.name, pkg.version, reason
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn internal_package_list(
+    manifest_path: Option<impl AsRef<Path>>,
+    alternate_registry: Option<&str>,
+    context: &StepContext,
+) -> Result<Vec<PackageInfo>> {
     info!("Building package graph");
-    let graph = get_package_graph(manifest_path)?;
+    let graph = get_package_graph_in(manifest_path, context)?;
 
-    process_publishable_packages(&graph, alternate_registry, |pkg| {
-        error!("{}({})", pkg.name(), pkg.version());
-        Ok(())
-    })
+    Ok(package_infos(&graph, alternate_registry))
+}
+
+/// The crates of the workspace of `graph` in the order in which they are published.
+fn package_infos(graph: &PackageGraph, alternate_registry: Option<&str>) -> Vec<PackageInfo> {
+    graph
+        .query_workspace()
+        .resolve_with_fn(|_, link| !link.dev_only())
+        .packages(DependencyDirection::Reverse)
+        .filter(|pkg| pkg.in_workspace())
+        .map(|pkg| {
+            let reason = unpublishable_reason(&pkg, alternate_registry);
+            PackageInfo {
+                name: pkg.name().to_owned(),
+                version: pkg.version().to_string(),
+                manifest_path: pkg.manifest_path().to_string(),
+                publishable: reason.is_none(),
+                reason,
+                dependencies: pkg
+                    .direct_links()
+                    .filter(|link| !link.dev_only() && link.to().in_workspace())
+                    .map(|link| link.to().name().to_owned())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// A selection of the members of a workspace.
+#[derive(Debug)]
+struct PackageSelection {
+    /// The names of the selected members, or `None` if every member is selected.
+    names: Option<HashSet<String>>,
+}
+
+impl PackageSelection {
+    /// Select every member of the workspace.
+    fn all() -> Self {
+        Self { names: None }
+    }
+
+    /// Select the members of the workspace of `graph` named in `packages`, or every
+    /// member if `packages` is `None`.
+    fn new(graph: &PackageGraph, packages: Option<&[String]>) -> Result<Self> {
+        let Some(packages) = packages else {
+            return Ok(Self::all());
+        };
+
+        for name in packages {
+            if graph.workspace().member_by_name(name).is_err() {
+                return Err(Error::unknown_package(name));
+            }
+        }
+
+        Ok(Self {
+            names: Some(packages.iter().cloned().collect()),
+        })
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names.as_ref().is_none_or(|names| names.contains(name))
+    }
 }
 
-fn get_package_graph(manifest_path: Option<impl AsRef<Path>>) -> Result<PackageGraph> {
+/// Build the package graph by running `cargo metadata` in the directory and environment
+/// of `context`.
+fn get_package_graph_in(
+    manifest_path: Option<impl AsRef<Path>>,
+    context: &StepContext,
+) -> Result<PackageGraph> {
     let manifest_path = manifest_path.as_ref().map(|path| path.as_ref());
 
-    let mut command = MetadataCommand::new();
+    let mut args = vec![
+        "metadata".to_owned(),
+        "--format-version".to_owned(),
+        "1".to_owned(),
+    ];
     if let Some(path) = manifest_path {
-        command.manifest_path(path);
+        args.push("--manifest-path".to_owned());
+        args.push(path.to_string_lossy().into_owned());
     }
+    let invocation = context.invocation(None, args, None);
 
     debug!("manifest_path: {:?}", manifest_path);
+    trace!("running: {:?}", invocation);
 
-    command.build_graph().map_err(|err| {
+    read_package_graph(context.runner().metadata(&invocation)).map_err(|err| {
         let path = match manifest_path {
             Some(path) => path.to_path_buf(),
             None => env::current_dir()
@@ -499,10 +2210,25 @@ fn get_package_graph(manifest_path: Option<impl AsRef<Path>>) -> Result<PackageG
                     PathBuf::from("unknown manifest")
                 }),
         };
-        Error::workspace_error(err, path).into()
+        Error::workspace_error(err, path)
     })
 }
 
+/// Build the package graph from the output of `cargo metadata`.
+fn read_package_graph(output: io::Result<Output>) -> result::Result<PackageGraph, GuppyError> {
+    let output = output.map_err(|err| GuppyError::CommandError(Box::new(err)))?;
+    if !output.status.success() {
+        let err = io::Error::other(format!(
+            "cargo metadata exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+        return Err(GuppyError::CommandError(Box::new(err)));
+    }
+
+    CargoMetadata::parse_json(String::from_utf8_lossy(&output.stdout))?.build_graph()
+}
+
 /// Is the source of the target of a dependencies publishable?
 ///
 /// The target of a dependencies must be available on `crates.io` for the depending
@@ -534,21 +2260,14 @@ fn link_is_publishable(link: &PackageLink) -> bool {
 
 /// Is a particular package publishable.
 ///
-/// A package is publishable if either publication is unrestricted or it can be
-/// published to one registry.
+/// A package is publishable if either publication is unrestricted or its list of
+/// registries includes `registry` (or `crates.io` if there is no `registry`).
 fn package_is_publishable(pkg: &PackageMetadata, registry: Option<&str>) -> bool {
     use guppy::graph::PackagePublish;
-    let registry_target = registry;
 
-    let result = match pkg.publish() {
-        guppy::graph::PackagePublish::Unrestricted => true,
-        guppy::graph::PackagePublish::Registries([registry]) => {
-            let registry_target = registry_target.unwrap_or(PackagePublish::CRATES_IO);
-            registry == registry_target
-        }
-        guppy::graph::PackagePublish::Registries([]) => false,
-        _ => todo!(),
-    };
+    let result = pkg
+        .publish()
+        .can_publish_to(registry.unwrap_or(PackagePublish::CRATES_IO));
 
     if result {
         trace!("package {} is publishable", pkg.name());
@@ -557,25 +2276,149 @@ fn package_is_publishable(pkg: &PackageMetadata, registry: Option<&str>) -> bool
     result
 }
 
-fn process_publishable_packages<F>(
-    graph: &PackageGraph,
+/// Why a package would not be published to `registry`, if it would not.
+fn unpublishable_reason(pkg: &PackageMetadata, registry: Option<&str>) -> Option<String> {
+    use guppy::graph::PackagePublish;
+
+    if package_is_publishable(pkg, registry) {
+        return None;
+    }
+
+    match pkg.publish() {
+        PackagePublish::Registries([]) => Some("publish is set to false".to_owned()),
+        _ => Some(format!(
+            "not published to {}",
+            registry.unwrap_or(PackagePublish::CRATES_IO)
+        )),
+    }
+}
+
+/// The publishable crates of the workspace in dependency order.
+fn publishable_packages<'g>(
+    graph: &'g PackageGraph,
     alternate_registry: Option<&str>,
-    mut f: F,
-) -> Result<()>
-where
-    F: FnMut(&PackageMetadata) -> Result<()>,
-{
-    info!("iterating the workspace crates in dependency order");
-    for pkg in graph
+) -> Vec<PackageMetadata<'g>> {
+    graph
         .query_workspace()
         .resolve_with_fn(|_, link| !link.dev_only())
         .packages(DependencyDirection::Reverse)
         .filter(|pkg| pkg.in_workspace() && package_is_publishable(pkg, alternate_registry))
-    {
-        f(&pkg)?;
+        .collect()
+}
+
+/// Group the publishable crates of the workspace into dependency levels.
+///
+/// A crate is placed one level after the deepest of the publishable workspace crates
+/// that it depends on, so all of the crates in a level can be published at the same
+/// time once the earlier levels are done. Within a level the crates keep their
+/// dependency order.
+fn publishable_package_levels<'g>(
+    graph: &'g PackageGraph,
+    alternate_registry: Option<&str>,
+) -> Vec<Vec<PackageMetadata<'g>>> {
+    let mut depths: HashMap<&'g PackageId, usize> = HashMap::new();
+    let mut levels: Vec<Vec<PackageMetadata<'g>>> = Vec::new();
+
+    for pkg in publishable_packages(graph, alternate_registry) {
+        let depth = pkg
+            .direct_links()
+            .filter(|link| !link.dev_only())
+            .filter_map(|link| depths.get(link.to().id()))
+            .map(|depth| depth + 1)
+            .max()
+            .unwrap_or_default();
+
+        trace!("package {} is in publish level {}", pkg.name(), depth);
+        depths.insert(pkg.id(), depth);
+        if levels.len() <= depth {
+            levels.resize_with(depth + 1, Vec::new);
+        }
+        levels[depth].push(pkg);
     }
 
-    Ok(())
+    levels
+}
+
+/// Run the `cargo publish` commands of one dependency level of crates, up to `jobs`
+/// at a time.
+///
+/// A report for each crate that was attempted is appended to `reports` in the order
+/// in which the crates finished, and each crate that was published is recorded in
+/// `journal`. Once a crate fails to publish, or cannot be recorded in the journal, no
+/// further crates are started and the first failure is returned after the crates
+/// that are already running have finished.
+fn publish_level(
+    level: &[PublishCommand],
+    jobs: usize,
+    journal: &Journal,
+    reports: &mut Vec<CrateReport>,
+) -> Result<()> {
+    let tagged = jobs > 1;
+    let queue = Mutex::new(level.iter());
+    let failed = AtomicBool::new(false);
+    let first_error = Mutex::new(None);
+    let finished = Mutex::new(Vec::with_capacity(level.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(level.len()) {
+            scope.spawn(|| {
+                while !failed.load(Ordering::SeqCst) {
+                    let Some(command) = queue.lock().expect("publish queue poisoned").next() else {
+                        break;
+                    };
+
+                    let (report, result) = command.run(tagged);
+                    // A resumed publish relies on the journal naming every published crate.
+                    let result =
+                        result.and_then(|()| journal.record(&report.name, &report.version));
+                    if let Err(err) = result {
+                        failed.store(true, Ordering::SeqCst);
+                        first_error
+                            .lock()
+                            .expect("publish results poisoned")
+                            .get_or_insert(err);
+                    }
+                    finished
+                        .lock()
+                        .expect("publish results poisoned")
+                        .push(report);
+                }
+            });
+        }
+    });
+
+    reports.extend(finished.into_inner().expect("publish results poisoned"));
+    match first_error.into_inner().expect("publish results poisoned") {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Find the configured main crate of a release.
+///
+/// The main crate must be a member of the workspace that is publishable to the target
+/// registry.
+fn configured_main_crate<'g>(
+    graph: &'g PackageGraph,
+    name: &str,
+    registry: Option<&str>,
+) -> Result<PackageMetadata<'g>> {
+    let pkg = graph.workspace().member_by_name(name).map_err(|_| {
+        Error::verify_error(format!(
+            "Main crate {} is not a member of the workspace.",
+            name
+        ))
+    })?;
+
+    if !package_is_publishable(&pkg, registry) {
+        return Err(Error::verify_error(format!(
+            "Main crate {} is not publishable to {}.",
+            name,
+            registry.unwrap_or("crates.io")
+        )));
+    }
+
+    Ok(pkg)
 }
 
 // Panics if id is not from graph
@@ -586,79 +2429,145 @@ fn get_crate_name<'a>(graph: &'a PackageGraph, id: &PackageId) -> &'a str {
         .name()
 }
 
-fn publish_package(pkg: &PackageMetadata, opts: &PublishArgs) -> Result<()> {
-    info!(
-        "Publishing version {} of {} to {} registry",
-        pkg.version(),
-        pkg.name(),
-        opts.registry.as_deref().unwrap_or("crates.io")
-    );
-
-    let cargo = env::var("CARGO")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from("cargo"));
-
-    let mut command = Command::new(cargo);
-    command
-        .args(["publish", "--manifest-path"])
-        .arg(pkg.manifest_path());
+/// The arguments to `cargo` that publish the crate `name` whose manifest is at
+/// `manifest_path`.
+fn publish_command_args(manifest_path: &str, name: &str, opts: &PublishArgs) -> Vec<String> {
+    let mut args = vec![
+        "publish".to_owned(),
+        "--manifest-path".to_owned(),
+        manifest_path.to_owned(),
+    ];
     if !opts.no_dirty.unwrap_or_default() {
-        command.arg("--allow-dirty");
+        args.push("--allow-dirty".to_owned());
     }
-    if let Some(features) = opts.features.as_ref().and_then(|f| f.get(pkg.name())) {
-        command.arg("--features");
-        command.args(features);
+    if let Some(features) = opts.features.as_ref().and_then(|f| f.get(name)) {
+        args.push("--features".to_owned());
+        args.extend(features.iter().cloned());
     }
     if let Some(registry) = opts.registry.as_ref() {
-        command.arg("--registry");
-        command.arg(registry);
+        args.push("--registry".to_owned());
+        args.push(registry.clone());
     }
 
-    trace!("running: {:?}", command);
-
-    let output = command
-        .output()
-        .map_err(|err| Error::cargo_publish(err, pkg.manifest_path().as_std_path()))?;
+    args
+}
 
-    let level = if output.status.success() {
-        Level::Trace
-    } else {
-        Level::Info
-    };
+/// A single `cargo publish` invocation.
+struct PublishCommand<'a> {
+    name: &'a str,
+    version: String,
+    registry: &'a str,
+    manifest_path: &'a Path,
+    args: Vec<String>,
+    current_dir: Option<&'a Path>,
+    timeout: Option<Duration>,
+    context: &'a StepContext,
+}
 
-    trace!("cargo publish stdout");
-    trace!("--------------------");
-    log_bytes(Level::Trace, &output.stdout);
+impl<'a> PublishCommand<'a> {
+    /// The `cargo publish` command for the crate `pkg` of the workspace.
+    fn for_package(
+        pkg: &PackageMetadata<'a>,
+        opts: &'a PublishArgs,
+        context: &'a StepContext,
+    ) -> Self {
+        PublishCommand {
+            name: pkg.name(),
+            version: pkg.version().to_string(),
+            registry: opts.registry.as_deref().unwrap_or("crates.io"),
+            manifest_path: pkg.manifest_path().as_std_path(),
+            args: publish_command_args(pkg.manifest_path().as_str(), pkg.name(), opts),
+            current_dir: None,
+            timeout: opts.timeout.map(|secs| Duration::from_secs(secs.into())),
+            context,
+        }
+    }
 
-    log!(level, "cargo publish stderr");
-    log!(level, "--------------------");
-    log_bytes(level, &output.stderr);
+    /// Run `cargo publish`.
+    ///
+    /// Each line of output from `cargo publish` is logged as it is written, prefixed
+    /// with the name of the crate so that concurrent publications remain readable.
+    /// The progress that cargo writes to stderr is logged at the info level and the
+    /// rest of its output at the trace level. If `tagged` is set then the messages
+    /// about the crate are prefixed in the same way. `cargo publish` is killed if it
+    /// runs for longer than the timeout. The report for the crate is returned whether
+    /// or not it was published.
+    fn run(&self, tagged: bool) -> (CrateReport, Result<()>) {
+        let tag = if tagged {
+            format!("[{}] ", self.name)
+        } else {
+            String::new()
+        };
 
-    if output.status.success() {
         info!(
-            "Published {}@{} to {} registry",
-            pkg.name(),
-            pkg.version(),
-            opts.registry.as_deref().unwrap_or("crates.io")
+            "{}Publishing version {} of {} to {} registry",
+            tag, self.version, self.name, self.registry
         );
-        Ok(())
-    } else {
-        error!(
-            "publishing package {} failed: {}\n{}",
-            pkg.name(),
-            output.status,
-            String::from_utf8_lossy(&output.stderr)
+
+        let invocation = CargoInvocation {
+            timeout: self.timeout,
+            ..self
+                .context
+                .invocation(Some(self.name), self.args.clone(), self.current_dir)
+        };
+
+        trace!("running: {:?}", invocation);
+
+        let report = CrateReport::new(
+            self.name,
+            self.version.as_str(),
+            self.registry,
+            PublishStatus::Failed,
         );
-        Err(Error::cargo_publish_status(
-            output.status,
-            pkg.manifest_path().as_std_path(),
-            &output.stderr,
-        )
-        .into())
+        let start = Instant::now();
+        let mut log_line = |stream, line: &str| match stream {
+            OutputStream::Stderr => info!("[{}] {}", self.name, line),
+            OutputStream::Stdout => trace!("[{}] {}", self.name, line),
+        };
+        let output = match self.context.runner().publish(&invocation, &mut log_line) {
+            Ok(output) => output,
+            Err(err) => {
+                let err = match self.timeout {
+                    Some(timeout) if err.kind() == io::ErrorKind::TimedOut => {
+                        error!(
+                            "publishing package {} did not finish within {} seconds",
+                            self.name,
+                            timeout.as_secs()
+                        );
+                        Error::cargo_publish_timeout(timeout, self.manifest_path)
+                    }
+                    _ => Error::cargo_publish(err, self.manifest_path),
+                };
+                return (report.duration(start.elapsed()), Err(err));
+            }
+        };
+        let report = report.duration(start.elapsed()).stderr(&output.stderr);
+
+        if output.status.success() {
+            info!(
+                "{}Published {}@{} to {} registry",
+                tag, self.name, self.version, self.registry
+            );
+            let report = CrateReport {
+                status: PublishStatus::Published,
+                ..report
+            };
+            (report, Ok(()))
+        } else {
+            error!(
+                "publishing package {} failed: {}\n{}",
+                self.name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let err =
+                Error::cargo_publish_status(output.status, self.manifest_path, &output.stderr);
+            (report, Err(err))
+        }
     }
 }
 
-fn log_bytes(level: Level, bytes: &[u8]) {
+fn log_bytes(level: Level, tag: &str, bytes: &[u8]) {
     let mut buffer = Cursor::new(bytes);
     let mut string = String::new();
 
@@ -666,7 +2575,7 @@ fn log_bytes(level: Level, bytes: &[u8]) {
         if size == 0 {
             return;
         }
-        log!(level, "{}", string);
+        log!(level, "{}{}", tag, string);
         string.clear();
     }
 }
@@ -677,15 +2586,16 @@ fn get_cargo_lock(path: &Path) -> PathBuf {
     path.parent().unwrap().join("Cargo.lock")
 }
 
+fn read_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|err| Error::file_read_error(err, path))
+}
+
 fn read_cargo_toml(path: &Path) -> Result<DocumentMut> {
-    fs::read_to_string(path)
-        .map_err(|err| Error::file_read_error(err, path))?
-        .parse()
-        .map_err(|err| Error::toml_error(err, path).into())
+    parse_cargo_toml(path, &read_file(path)?)
 }
 
-fn write_cargo_toml(path: &Path, cargo: DocumentMut) -> Result<()> {
-    fs::write(path, cargo.to_string()).map_err(|err| Error::file_write_error(err, path).into())
+fn parse_cargo_toml(path: &Path, contents: &str) -> Result<DocumentMut> {
+    contents.parse().map_err(|err| Error::toml_error(err, path))
 }
 
 fn get_top_table<'a>(doc: &'a DocumentMut, key: &str) -> Option<&'a Table> {
@@ -739,7 +2649,7 @@ fn dependency_has_version(
         .and_then(Item::as_table_like)
         .and_then(|dep| dep.get("version"))
         .map(|_| ())
-        .ok_or_else(|| Error::bad_dependency(link, typ).into())
+        .ok_or_else(|| Error::bad_dependency(link, typ))
 }
 
 fn set_package_version(doc: &mut DocumentMut, version: &str) -> result::Result<(), CargoTomlError> {
@@ -926,7 +2836,7 @@ impl fmt::Display for DependencyType {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug)]
 struct Release {
     name: String,
     url: Option<Url>,
@@ -954,6 +2864,18 @@ impl Release {
         })
     }
 
+    fn new_from_template(
+        name: impl AsRef<str>,
+        template: &UrlTemplate,
+        main_crate: &str,
+        version: &str,
+    ) -> Result<Self> {
+        Ok(Self {
+            name: name.as_ref().to_owned(),
+            url: Some(template.render(main_crate, version)?),
+        })
+    }
+
     fn new_crates_io_release(name: impl AsRef<str>, main_crate: impl AsRef<str>) -> Result<Self> {
         let base = Url::parse("https://crates.io/crates/").map_err(Error::url_parse_error)?;
         let url = base
@@ -966,3 +2888,106 @@ impl Release {
         })
     }
 }
+
+/// The links to the published crates of a release.
+#[derive(Debug)]
+struct ReleaseLinks {
+    /// Whether the crates are published to `crates.io`.
+    crates_io: bool,
+
+    /// The configured template for the web url of a published crate.
+    template: Option<UrlTemplate>,
+
+    /// Whether to include docs.rs links.
+    docs_rs: bool,
+}
+
+impl ReleaseLinks {
+    fn new(
+        config: &WorkspaceConfig,
+        registry: Option<&str>,
+        url_template: Option<&str>,
+        docs_rs: Option<bool>,
+    ) -> Result<Self> {
+        let template = url_template
+            .or_else(|| config.url_template(registry))
+            .map(UrlTemplate::parse)
+            .transpose()?;
+
+        Ok(Self {
+            crates_io: registry.is_none(),
+            template,
+            docs_rs: registry.is_none() && docs_rs.or(config.docs_rs).unwrap_or_default(),
+        })
+    }
+
+    /// The release record for a release whose main crate is `main_crate`.
+    fn release(&self, name: String, main_crate: &str, version: &str) -> Result<Release> {
+        match &self.template {
+            Some(template) => Release::new_from_template(name, template, main_crate, version),
+            None if self.crates_io => Release::new_crates_io_release(name, main_crate),
+            None => Release::new::<&str>(name, None, main_crate),
+        }
+    }
+
+    /// The url of a published version of a crate on its registry, if it is known.
+    fn crate_url(&self, name: &str, version: &str) -> Result<Option<String>> {
+        match &self.template {
+            Some(template) => Ok(Some(template.render(name, version)?.into())),
+            None if self.crates_io => Ok(Some(format!(
+                "https://crates.io/crates/{}/{}",
+                name, version
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// The docs.rs url of a published version of a crate, if docs.rs links are enabled.
+    fn docs_url(&self, name: &str, version: &str) -> Option<String> {
+        self.docs_rs
+            .then(|| format!("https://docs.rs/{}/{}", name, version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn test_data_graph(dir: &str) -> PackageGraph {
+        let manifest_path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "test_data", dir, "Cargo.toml"]
+            .iter()
+            .collect();
+
+        get_package_graph_in(Some(manifest_path), &StepContext::default())
+            .expect("Couldn't build graph")
+    }
+
+    #[test]
+    fn publishable_package_levels_group_independent_crates() {
+        let graph = test_data_graph("dependencies");
+
+        let levels: Vec<Vec<&str>> = publishable_package_levels(&graph, None)
+            .iter()
+            .map(|level| {
+                let mut names: Vec<_> = level.iter().map(|pkg| pkg.name()).collect();
+                names.sort_unstable();
+                names
+            })
+            .collect();
+
+        assert_eq!(levels, vec![vec!["build1", "dep1"], vec!["dependencies"]]);
+    }
+
+    #[test]
+    fn publishable_package_levels_for_single_crate() {
+        let graph = test_data_graph("basic");
+
+        let levels = publishable_package_levels(&graph, None);
+
+        assert_eq!(levels.len(), 1);
+        assert_eq!(levels[0].len(), 1);
+        assert_eq!(levels[0][0].name(), "basic");
+    }
+}
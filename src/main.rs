@@ -7,18 +7,21 @@
 // except according to those terms
 
 use std::{
-    fs::File,
+    fs::{self, File},
     path::{Path, PathBuf},
 };
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use clap::{builder::TypedValueParser, crate_version, Parser};
 use log::Level;
 
 mod logger;
 
 use semantic_release_cargo::{
-    list_packages_with_arguments, prepare, publish, verify_conditions_with_alternate, PublishArgs,
+    affected, apply, generate_notes, list_packages_with_arguments, next_version, plan,
+    prepare_diff, prepare_with_args, publish, tag, verify_conditions_with_args, yank,
+    GenerateNotesArgs, NextVersionArgs, PrepareArgs, PublishArgs, ReleasePlan, TagArgs, VerifyArgs,
+    YankArgs,
 };
 
 /// Run semantic-release steps in the context of a cargo based Rust project.
@@ -70,11 +73,15 @@ enum Subcommand {
     ///        packages in the root crate's workspace.
     ///     3. That it can parse the version for packages in the workspace in all of
     ///        the `Cargo.toml` files that form part of the workspace.
+    ///     4. That the release configuration in the workspace metadata, and any url
+    ///        template for the release record, are valid.
+    ///     5. That the main crate of the release record, if one is configured, is
+    ///        publishable to the target registry.
     ///
     /// This implements the `verifyConditions` step for `semantic-release` for a
     /// Cargo-based Rust workspace.
     #[clap(verbatim_doc_comment)]
-    VerifyConditions(CommonOpt),
+    VerifyConditions(VerifyOpt),
 
     /// Prepare the Rust workspace for a release.
     ///
@@ -83,6 +90,11 @@ enum Subcommand {
     /// version field of any workspace-relative path dependencies and
     /// build-dependencies.
     ///
+    /// With the `check` flag nothing is written. Instead a diff of the changes that
+    /// would be made is printed, and the subcommand fails if there are any. With the
+    /// `commit` flag the changed files, and nothing else, are committed to git so
+    /// that `publish` can run with `no-dirty`.
+    ///
     /// This implements the `prepare` step for `semantic-release` for a Cargo-based
     /// Rust workspace.
     Prepare(PrepareOpt),
@@ -93,11 +105,81 @@ enum Subcommand {
     /// crates.io except crates with the `package.publish` field set to `false` or
     /// set to any registries other than just crates.io. By default this will publish
     /// with the `allow-dirty` flag but this can be excluded with the `no-dirty`
-    /// flag to this subcommand.
+    /// flag to this subcommand. Independent crates can be published at the same
+    /// time with the `jobs` flag, and a publish that was interrupted can be
+    /// continued with the `resume` flag.
     ///
     /// This implements the `publish` step for `semantic-release` for a Cargo-based
     /// Rust workspace.
     Publish(PublishOpt),
+
+    /// Plan a release of the Rust workspace without changing anything.
+    ///
+    /// The plan records the crates of the workspace, the order in which they will
+    /// be published, the edits that `prepare` would make to the manifests,
+    /// lockfiles and, with the `changelog` flag, changelogs, the `cargo publish`
+    /// commands that `publish` would run and the tags it would create. It is written as JSON so that it can be reviewed and
+    /// later run with `apply`.
+    Plan(PlanOpt),
+
+    /// Apply a release plan made by the `plan` subcommand.
+    ///
+    /// Applying the plan writes its edits and then runs its `cargo publish`
+    /// commands level by level, as `publish` does, and creates its tags. Nothing
+    /// is done if the manifests, lockfiles, changelogs or cargo configuration of
+    /// the workspace have changed since the plan was made.
+    Apply(ApplyOpt),
+
+    /// Yank a version of the publishable crates of the Rust workspace.
+    ///
+    /// This rolls back a release that went wrong after some of its crates were
+    /// published. The crates are yanked in the reverse of the order in which they
+    /// are published, and crates that were never published at the version are
+    /// skipped. With the `undo` flag the version is un-yanked instead.
+    Yank(YankOpt),
+
+    /// Print the next release version from the conventional commits since the
+    /// last release.
+    ///
+    /// The last release is the highest version among the release tags reachable
+    /// from `HEAD`. A breaking change calls for a major release (a minor release
+    /// while the major version is 0), a `feat` for a minor release and a `fix` or
+    /// `perf` for a patch release. Without a previous release the next version is
+    /// 1.0.0. Nothing is printed if no commit calls for a release.
+    ///
+    /// This gives the version that the `analyzeCommits` step of `semantic-release`
+    /// would choose, for use without `semantic-release`.
+    NextVersion(NextVersionOpt),
+
+    /// Print the crates of the Rust workspace affected by the changes since a git
+    /// ref.
+    ///
+    /// A crate is affected if a file in its directory changed since the base ref
+    /// (including uncommitted changes), or if it depends on an affected crate. The
+    /// result is printed as JSON, and the names of the affected crates can be passed
+    /// to the `package` flag of `prepare`, `publish` and `plan`.
+    Affected(AffectedOpt),
+
+    /// Generate release notes with a section for each crate of the Rust workspace.
+    ///
+    /// The notes cover the commits in a git revision range, such as
+    /// `v1.2.0..HEAD`. A commit belongs to the crates whose directories contain the
+    /// files it touched and to the crate named by its conventional commit scope.
+    /// Each crate with a breaking change, feature, bug fix or performance
+    /// improvement gets a Markdown section headed by its name and new version,
+    /// linked to the published crate when its url is known.
+    ///
+    /// This implements the `generateNotes` step for `semantic-release` for a
+    /// Cargo-based Rust workspace.
+    GenerateNotes(GenerateNotesOpt),
+
+    /// Create an annotated git tag for each publishable crate of the Rust workspace.
+    ///
+    /// Each tag points at `HEAD` and is named by the tag format (by default
+    /// `{name}-v{version}`) with the name and current version of the crate. Tags
+    /// that already point at `HEAD` are kept, and nothing is tagged if any tag
+    /// already points at another commit.
+    Tag(TagOpt),
 }
 
 #[derive(Parser)]
@@ -111,6 +193,21 @@ struct CommonOpt {
     registry: Option<String>,
 }
 
+#[derive(Parser)]
+struct VerifyOpt {
+    #[clap(flatten)]
+    common: CommonOpt,
+
+    /// The template for the web url of a published crate, such as
+    /// `https://crates.internal/crates/{name}/{version}`.
+    #[clap(long)]
+    url_template: Option<String>,
+
+    /// The crate linked in the release record.
+    #[clap(long)]
+    main_crate: Option<String>,
+}
+
 #[derive(Parser)]
 struct PrepareOpt {
     #[clap(flatten)]
@@ -118,10 +215,73 @@ struct PrepareOpt {
 
     /// The version to set in all crates in the workspace.
     next_version: String,
+
+    /// Check that the workspace is already prepared for the version, without
+    /// writing any files.
+    #[clap(long)]
+    check: bool,
+
+    /// The crates to prepare, as a comma separated list (defaults to all of the
+    /// crates in the workspace).
+    #[clap(long = "package", value_delimiter = ',')]
+    packages: Vec<String>,
+
+    #[clap(flatten)]
+    changelog: ChangelogOpt,
+
+    /// Commit the files that were changed, and nothing else, to git.
+    #[clap(long, conflicts_with = "check")]
+    commit: bool,
+
+    /// The template for the commit message, in which `{version}` is replaced by the
+    /// new version (defaults to `chore(release): {version}`).
+    #[clap(long, requires = "commit")]
+    commit_message: Option<String>,
+}
+
+// The changelog options of `prepare`, which a release plan also records.
+#[derive(Parser)]
+struct ChangelogOpt {
+    /// Add a section for the release to the changelog of each prepared crate.
+    ///
+    /// The changelog is `CHANGELOG.md` in the directory of the crate unless the
+    /// crate sets `changelog` in `[package.metadata.semantic-release-cargo]`.
+    #[clap(long)]
+    changelog: bool,
+
+    /// The files holding the changelog notes of crates.
+    /// This is a comma separated list of key-value pairs where the key is the
+    /// name of the crate and the value a file with its notes.
+    #[clap(long, value_parser = parse_key_val::<String, PathBuf>, value_delimiter = ',')]
+    changelog_notes: Vec<(String, PathBuf)>,
+
+    /// The git revision range, such as `v1.2.0..HEAD`, from which to generate the
+    /// changelog notes of crates that are not given notes.
+    #[clap(long)]
+    changelog_range: Option<String>,
+
+    /// The date in the heading of the new changelog sections (defaults to today).
+    #[clap(long)]
+    changelog_date: Option<String>,
+
+    /// Leave the changelogs of crates without any notes untouched.
+    #[clap(long)]
+    skip_unchanged: bool,
 }
 
 #[derive(Parser)]
 struct PublishOpt {
+    #[clap(flatten)]
+    release: ReleaseOpt,
+
+    /// Resume an interrupted publish, skipping the crates that it already published.
+    #[clap(long)]
+    resume: bool,
+}
+
+// The options of `publish` that a release plan records.
+#[derive(Parser)]
+struct ReleaseOpt {
     #[clap(flatten)]
     common: CommonOpt,
 
@@ -136,6 +296,140 @@ struct PublishOpt {
     /// the `foo` package and the `qux` feature for the `baz` package.
     #[clap(long, value_parser = parse_key_val::<String, String>, value_delimiter = ',')]
     features: Vec<(String, String)>,
+
+    /// The maximum number of crates to publish at the same time.
+    ///
+    /// Crates are published in dependency levels: a crate is only published once
+    /// all of the workspace crates that it depends on have been published.
+    #[clap(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: u32,
+
+    /// The template for the web url of a published crate, such as
+    /// `https://crates.internal/crates/{name}/{version}`.
+    #[clap(long)]
+    url_template: Option<String>,
+
+    /// Include docs.rs links for crates published to crates.io.
+    #[clap(long)]
+    docs_rs: bool,
+
+    /// The crate linked in the release record.
+    #[clap(long)]
+    main_crate: Option<String>,
+
+    /// The crates to publish, as a comma separated list (defaults to all of the
+    /// publishable crates in the workspace).
+    #[clap(long = "package", value_delimiter = ',')]
+    packages: Vec<String>,
+
+    /// Create an annotated git tag for each published crate once every crate is
+    /// published.
+    #[clap(long)]
+    tag: bool,
+
+    /// The format of the tags, such as `{name}-v{version}`.
+    #[clap(long)]
+    tag_format: Option<String>,
+
+    /// Kill `cargo publish` for a crate if it runs for longer than this many seconds.
+    #[clap(long, value_name = "SECONDS", value_parser = clap::value_parser!(u32).range(1..))]
+    timeout: Option<u32>,
+}
+
+#[derive(Parser)]
+struct PlanOpt {
+    #[clap(flatten)]
+    release: ReleaseOpt,
+
+    /// The version to set in all crates in the workspace.
+    next_version: String,
+
+    #[clap(flatten)]
+    changelog: ChangelogOpt,
+
+    /// The file to write the plan to instead of standard out.
+    #[clap(long)]
+    plan_file: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct ApplyOpt {
+    /// The path to the `Cargo.toml` file for the root of the workspace.
+    #[clap(long)]
+    manifest_path: Option<PathBuf>,
+
+    /// The file containing the plan to apply.
+    plan_file: PathBuf,
+}
+
+#[derive(Parser)]
+struct YankOpt {
+    #[clap(flatten)]
+    common: CommonOpt,
+
+    /// The version to yank.
+    version: String,
+
+    /// Un-yank the version instead of yanking it.
+    #[clap(long)]
+    undo: bool,
+}
+
+#[derive(Parser)]
+struct NextVersionOpt {
+    /// The path to the git repository.
+    #[clap(long)]
+    repository: Option<PathBuf>,
+
+    /// The prefix of the release tags, followed by the version.
+    #[clap(long, default_value = "v")]
+    tag_prefix: String,
+}
+
+#[derive(Parser)]
+struct AffectedOpt {
+    /// The path to the `Cargo.toml` file for the root of the workspace.
+    #[clap(long)]
+    manifest_path: Option<PathBuf>,
+
+    /// The git ref to compare the workspace with, such as the last release tag.
+    base: String,
+}
+
+#[derive(Parser)]
+struct GenerateNotesOpt {
+    #[clap(flatten)]
+    common: CommonOpt,
+
+    /// The git revision range of the release, such as `v1.2.0..HEAD`.
+    range: String,
+
+    /// The version of the crates in the release.
+    next_version: String,
+
+    /// The template for the web url of a published crate, such as
+    /// `https://crates.internal/crates/{name}/{version}`.
+    #[clap(long)]
+    url_template: Option<String>,
+
+    /// Include docs.rs links for crates published to crates.io.
+    #[clap(long)]
+    docs_rs: bool,
+}
+
+#[derive(Parser)]
+struct TagOpt {
+    #[clap(flatten)]
+    common: CommonOpt,
+
+    /// The format of the tags, such as `{name}-v{version}`.
+    #[clap(long)]
+    tag_format: Option<String>,
+
+    /// The crates to tag, as a comma separated list (defaults to all of the
+    /// publishable crates in the workspace).
+    #[clap(long = "package", value_delimiter = ',')]
+    packages: Vec<String>,
 }
 
 /// Parse a single key-value pair
@@ -163,32 +457,196 @@ impl Subcommand {
                 opt.registry.as_deref(),
                 opt.manifest_path(),
             )?),
-            VerifyConditions(opt) => Ok(verify_conditions_with_alternate(
-                opt.registry.as_deref(),
-                opt.manifest_path(),
+            VerifyConditions(opt) => Ok(verify_conditions_with_args(
+                opt.common.manifest_path(),
+                &VerifyArgs {
+                    registry: opt.common.registry.clone(),
+                    url_template: opt.url_template.clone(),
+                    main_crate: opt.main_crate.clone(),
+                },
             )?),
-            Prepare(opt) => Ok(prepare(
+            Prepare(opt) if opt.check => {
+                let diff =
+                    prepare_diff(opt.common.manifest_path(), &opt.next_version, &opt.args()?)?;
+                if !diff.is_empty() {
+                    print!("{}", diff);
+                    bail!(
+                        "The workspace is not prepared for version {}",
+                        opt.next_version
+                    );
+                }
+                Ok(())
+            }
+            Prepare(opt) => Ok(prepare_with_args(
                 opt.common.manifest_path(),
                 opt.next_version.clone(),
+                &opt.args()?,
             )?),
-            Publish(opt) => Ok(publish(
-                opt.common.manifest_path(),
-                &PublishArgs {
-                    no_dirty: Some(opt.no_dirty),
-                    features: Some(opt.features.iter().cloned().fold(
-                        Default::default(),
-                        |mut a, (k, v)| {
-                            a.entry(k).or_default().push(v);
-                            a
-                        },
-                    )),
-                    registry: opt.common.registry.clone(),
+            Publish(opt) => {
+                publish(opt.release.common.manifest_path(), &opt.args())?;
+                Ok(())
+            }
+            Plan(opt) => {
+                let plan = plan(
+                    opt.release.common.manifest_path(),
+                    &opt.next_version,
+                    &opt.changelog.args(&opt.release.packages)?,
+                    &opt.release.args(),
+                )?;
+                let json = serde_json::to_string_pretty(&plan)?;
+                match &opt.plan_file {
+                    Some(path) => fs::write(path, json + "\n")
+                        .with_context(|| format!("Failed to write plan file {}", path.display())),
+                    None => {
+                        println!("{}", json);
+                        Ok(())
+                    }
+                }
+            }
+            Apply(opt) => {
+                let json = fs::read_to_string(&opt.plan_file).with_context(|| {
+                    format!("Failed to read plan file {}", opt.plan_file.display())
+                })?;
+                let plan: ReleasePlan = serde_json::from_str(&json).with_context(|| {
+                    format!("Failed to parse plan file {}", opt.plan_file.display())
+                })?;
+                apply(opt.manifest_path.as_deref(), &plan)?;
+                Ok(())
+            }
+            Yank(opt) => {
+                yank(
+                    opt.common.manifest_path(),
+                    &opt.version,
+                    &YankArgs {
+                        registry: opt.common.registry.clone(),
+                        undo: Some(opt.undo),
+                    },
+                )?;
+                Ok(())
+            }
+            NextVersion(opt) => {
+                let version = next_version(
+                    opt.repository.as_deref(),
+                    &NextVersionArgs {
+                        tag_prefix: Some(opt.tag_prefix.clone()),
+                    },
+                )?;
+                if let Some(version) = version {
+                    println!("{}", version);
+                }
+                Ok(())
+            }
+            Affected(opt) => {
+                let affected = affected(opt.manifest_path.as_deref(), &opt.base)?;
+                println!("{}", serde_json::to_string_pretty(&affected)?);
+                Ok(())
+            }
+            GenerateNotes(opt) => {
+                let notes = generate_notes(
+                    opt.common.manifest_path(),
+                    &opt.range,
+                    &opt.next_version,
+                    &GenerateNotesArgs {
+                        registry: opt.common.registry.clone(),
+                        url_template: opt.url_template.clone(),
+                        docs_rs: opt.docs_rs.then_some(true),
+                    },
+                )?;
+                print!("{}", notes);
+                Ok(())
+            }
+            Tag(opt) => {
+                let report = tag(
+                    opt.common.manifest_path(),
+                    &TagArgs {
+                        registry: opt.common.registry.clone(),
+                        tag_format: opt.tag_format.clone(),
+                        packages: packages(&opt.packages),
+                    },
+                )?;
+                for tag in report.tags {
+                    println!("{}", tag.tag);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl PrepareOpt {
+    fn args(&self) -> Result<PrepareArgs, Error> {
+        Ok(PrepareArgs {
+            commit: self.commit.then_some(true),
+            commit_message: self.commit_message.clone(),
+            ..self.changelog.args(&self.packages)?
+        })
+    }
+}
+
+impl ChangelogOpt {
+    fn args(&self, names: &[String]) -> Result<PrepareArgs, Error> {
+        let changelog_notes = self
+            .changelog_notes
+            .iter()
+            .map(|(name, path)| {
+                let notes = fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read changelog notes {}", path.display())
+                })?;
+                Ok((name.clone(), notes))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(PrepareArgs {
+            packages: packages(names),
+            changelog: self.changelog.then_some(true),
+            changelog_notes: Some(changelog_notes),
+            changelog_range: self.changelog_range.clone(),
+            changelog_date: self.changelog_date.clone(),
+            skip_unchanged: self.skip_unchanged.then_some(true),
+            ..Default::default()
+        })
+    }
+}
+
+impl PublishOpt {
+    fn args(&self) -> PublishArgs {
+        PublishArgs {
+            resume: self.resume.then_some(true),
+            ..self.release.args()
+        }
+    }
+}
+
+impl ReleaseOpt {
+    fn args(&self) -> PublishArgs {
+        PublishArgs {
+            no_dirty: Some(self.no_dirty),
+            features: Some(self.features.iter().cloned().fold(
+                Default::default(),
+                |mut a, (k, v)| {
+                    a.entry(k).or_default().push(v);
+                    a
                 },
-            )?),
+            )),
+            registry: self.common.registry.clone(),
+            jobs: Some(self.jobs),
+            url_template: self.url_template.clone(),
+            docs_rs: self.docs_rs.then_some(true),
+            main_crate: self.main_crate.clone(),
+            resume: None,
+            packages: packages(&self.packages),
+            tag: self.tag.then_some(true),
+            tag_format: self.tag_format.clone(),
+            timeout: self.timeout,
         }
     }
 }
 
+/// The selected packages, or `None` to select all of them.
+fn packages(packages: &[String]) -> Option<Vec<String>> {
+    (!packages.is_empty()).then(|| packages.to_vec())
+}
+
 fn main() -> Result<(), Error> {
     let opt: Opt = Opt::parse();
 
@@ -0,0 +1,551 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The napi bindings of the library for the `semantic-release` plugin.
+//!
+//! These are thin wrappers around the Rust API of the crate, which is the same with
+//! or without the `napi-rs` feature. They take their options as JavaScript objects
+//! and look for the workspace in the directory of the step context or, without one,
+//! in the current directory.
+//!
+//! The `semantic-release` steps, and the other functions that run cargo or git or
+//! write to the workspace, return promises. Each runs on a worker thread of the libuv
+//! thread pool so that the Node event loop keeps running while cargo does. One that
+//! fails rejects with an `Error` whose `code` property identifies the kind of
+//! failure, such as `EVERIFY`.
+
+use napi::{
+    bindgen_prelude::{AsyncTask, ToNapiValue, TypeName},
+    Env, Task,
+};
+use napi_derive::napi;
+
+use log::info;
+
+pub use crate::js_logger::{register_logger, LoggerOptions};
+use crate::{
+    internal_affected, internal_apply, internal_fail, internal_generate_notes,
+    internal_package_list, internal_plan, internal_prepare, internal_prepare_diff,
+    internal_publish, internal_tag, internal_verify_conditions, internal_verify_release,
+    internal_yank, AffectedPackages, Error, FailReport, GenerateNotesArgs, ListPackagesArgs,
+    NextVersionArgs, PackageInfo, PrepareArgs, PublishArgs, PublishReport, ReleasePlan, Result,
+    StepOptions, TagArgs, TagReport, VerifyArgs, VerifyReleaseArgs, YankArgs, YankReport,
+};
+
+/// The `code` of the errors that do not come from the step itself.
+const UNKNOWN_ERROR_CODE: &str = "EUNKNOWN";
+
+/// A step to run on a worker thread.
+type Step<T> = Box<dyn FnOnce() -> Result<T> + Send>;
+
+/// A `semantic-release` step that runs on a worker thread and settles a promise.
+pub struct StepTask<T> {
+    step: Option<Step<T>>,
+    code: &'static str,
+    report: fn(&Error) -> Option<T>,
+    failed_report: Option<T>,
+}
+
+impl<T> StepTask<T> {
+    fn new(step: impl FnOnce() -> Result<T> + Send + 'static) -> AsyncTask<Self>
+    where
+        T: ToNapiValue + TypeName + Send + 'static,
+    {
+        Self::with_report(step, |_| None)
+    }
+
+    /// A step whose error, if it fails, is given the `report` property found by
+    /// `report`.
+    fn with_report(
+        step: impl FnOnce() -> Result<T> + Send + 'static,
+        report: fn(&Error) -> Option<T>,
+    ) -> AsyncTask<Self>
+    where
+        T: ToNapiValue + TypeName + Send + 'static,
+    {
+        AsyncTask::new(StepTask {
+            step: Some(Box::new(step)),
+            code: UNKNOWN_ERROR_CODE,
+            report,
+            failed_report: None,
+        })
+    }
+}
+
+impl<T> Task for StepTask<T>
+where
+    T: ToNapiValue + TypeName + Send + 'static,
+{
+    type Output = T;
+    type JsValue = T;
+
+    fn compute(&mut self) -> napi::Result<T> {
+        let step = self.step.take().expect("step already run");
+        step().map_err(|err| {
+            self.code = err.code();
+            self.failed_report = (self.report)(&err);
+            anyhow::Error::from(err).into()
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, output: T) -> napi::Result<T> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<T> {
+        let mut error = env.create_error(err)?;
+        error.set_named_property("code", env.create_string(self.code)?)?;
+        if let Some(report) = self.failed_report.take() {
+            error.set_named_property("report", report)?;
+        }
+
+        Err(error.into_unknown().into())
+    }
+}
+
+#[napi(object)]
+#[derive(Debug, Default)]
+/// The release that `semantic-release` is making, from `context.nextRelease`.
+pub struct NextRelease {
+    /// The version of the release.
+    pub version: String,
+
+    /// The git tag of the release.
+    pub git_tag: Option<String>,
+
+    /// The git commit of the release.
+    pub git_head: Option<String>,
+
+    /// The release channel of the release.
+    pub channel: Option<String>,
+
+    /// The release notes of the release.
+    pub notes: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Default)]
+/// The last release made by `semantic-release`, from `context.lastRelease`.
+pub struct LastRelease {
+    /// The version of the release.
+    pub version: Option<String>,
+
+    /// The git tag of the release.
+    pub git_tag: Option<String>,
+
+    /// The git commit of the release.
+    pub git_head: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Default)]
+/// The fields of the `semantic-release` context of the `success` step.
+pub struct SuccessContext {
+    /// The release that was made, from `context.nextRelease`.
+    pub next_release: Option<NextRelease>,
+
+    /// The release records that `publish` returned, from `context.releases`.
+    pub releases: Vec<PublishReport>,
+}
+
+/// Verify that the conditions for a release are satisfied.
+///
+/// The conditions are those checked by `verify_conditions_with_args` for the
+/// workspace and environment of `context`. The registry of `opts` defaults to the one
+/// in `context`.
+///
+/// This implements the `verifyConditions` step for `semantic-release` for a
+/// Cargo-based rust workspace.
+#[napi]
+pub fn verify_conditions(
+    opts: Option<VerifyArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<()>> {
+    let context = context.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+
+    StepTask::new(move || {
+        internal_verify_conditions(context.manifest_path(), &opts, &context.context())
+    })
+}
+
+/// Prepare the Rust workspace for a release.
+///
+/// This sets the version of the crates selected by `opts`, and of the dependencies on
+/// them, to `next_release_version` in the workspace of `context`, as described for
+/// the `prepare` function of the library.
+///
+/// This implements the `prepare` step for `semantic-release` for a Cargo-based Rust
+/// workspace.
+#[napi]
+pub fn prepare(
+    next_release_version: String,
+    opts: Option<PrepareArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<()>> {
+    let context = context.unwrap_or_default();
+    let opts = opts.unwrap_or_default();
+
+    StepTask::new(move || {
+        internal_prepare(
+            context.manifest_path().as_deref(),
+            next_release_version,
+            &opts,
+            &context.context(),
+        )
+    })
+}
+
+/// Publish the publishable crates from the workspace.
+///
+/// This publishes the crates of the workspace of `context`, running `cargo publish` in
+/// its directory and environment, and resolves to the [`PublishReport`] that doubles
+/// as the release record for `semantic-release`. The registry of `opts` defaults to
+/// the one in `context`. If a crate fails to publish, the error that the promise
+/// rejects with has a `report` property with the outcome for each crate.
+///
+/// This implements the `publish` step for `semantic-release` for a Cargo-based
+/// Rust workspace.
+#[napi]
+pub fn publish(
+    opts: Option<PublishArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<PublishReport>> {
+    let context = context.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+
+    StepTask::with_report(
+        move || {
+            internal_publish(
+                context.manifest_path().as_deref(),
+                &opts,
+                &context.context(),
+            )
+        },
+        |err| err.publish_report().cloned(),
+    )
+}
+
+/// Verify that the version of `next_release` can be released from the workspace.
+///
+/// The version must be a valid semantic version that is not earlier than the version
+/// of any of the crates to release and, unless `opts` turns off the registry check,
+/// must not be published already. The registry of `opts` defaults to the one in
+/// `context`.
+///
+/// This implements the `verifyRelease` step for `semantic-release` for a Cargo-based
+/// Rust workspace.
+#[napi]
+pub fn verify_release(
+    next_release: NextRelease,
+    opts: Option<VerifyReleaseArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<()>> {
+    let context = context.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+
+    StepTask::new(move || {
+        internal_verify_release(
+            context.manifest_path().as_deref(),
+            &next_release.version,
+            &opts,
+            &context.context(),
+        )
+    })
+}
+
+/// Summarize the crates published by the release.
+///
+/// The summary of each release record in `context` is logged and the summaries are
+/// returned.
+///
+/// This implements the `success` step for `semantic-release` for a Cargo-based Rust
+/// workspace.
+#[napi]
+pub fn success(context: SuccessContext) -> String {
+    let summary = context
+        .releases
+        .iter()
+        .map(PublishReport::summary)
+        .collect::<Vec<_>>()
+        .join("\n");
+    info!("{}", summary);
+
+    summary
+}
+
+/// Report what a failed release left published.
+///
+/// The crates recorded in the publish journal of the workspace of `context` are logged
+/// with a hint to resume the publish or to yank them, and the report is written as
+/// `rollback.json` next to the journal.
+///
+/// This implements the `fail` step for `semantic-release` for a Cargo-based Rust
+/// workspace.
+#[napi]
+pub fn fail(context: Option<StepOptions>) -> AsyncTask<StepTask<FailReport>> {
+    let context = context.unwrap_or_default();
+
+    StepTask::new(move || internal_fail(context.manifest_path().as_deref(), &context.context()))
+}
+
+/// Compute the changes that preparing the Rust workspace for a release would make.
+///
+/// This runs the same updates as [`prepare`] against in-memory copies of the files in
+/// the workspace of `context` and resolves to a unified diff of the changes, without
+/// writing anything. The diff is empty if the workspace is already prepared for
+/// `next_release_version`.
+#[napi]
+pub fn prepare_diff(
+    next_release_version: String,
+    opts: Option<PrepareArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<String>> {
+    let context = context.unwrap_or_default();
+    let opts = opts.unwrap_or_default();
+
+    StepTask::new(move || {
+        internal_prepare_diff(
+            context.manifest_path().as_deref(),
+            &next_release_version,
+            &opts,
+            &context.context(),
+        )
+    })
+}
+
+/// Plan a release of the workspace without changing anything.
+///
+/// The plan records the edits to the manifests, lockfiles and changelogs that
+/// [`prepare`] would make to the workspace of `context` for `next_release_version`
+/// with `prepare_opts`, and the `cargo publish` commands and tags that [`publish`]
+/// would then run and create with `opts`, along with a fingerprint of the workspace.
+/// It can be serialized to JSON, reviewed, and later run with [`apply`]. The `commit`
+/// and `resume` options are not used, and the registry of `opts` defaults to the one
+/// in `context`.
+#[napi]
+pub fn plan(
+    next_release_version: String,
+    prepare_opts: Option<PrepareArgs>,
+    opts: Option<PublishArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<ReleasePlan>> {
+    let context = context.unwrap_or_default();
+    let prepare_opts = prepare_opts.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+
+    StepTask::new(move || {
+        internal_plan(
+            context.manifest_path().as_deref(),
+            &next_release_version,
+            &prepare_opts,
+            &opts,
+            &context.context(),
+        )
+    })
+}
+
+/// Apply a release plan made by [`plan`].
+///
+/// Applying the plan writes its edits to the workspace of `context` and then runs its
+/// `cargo publish` commands level by level, as [`publish`] does, and creates its tags
+/// once every crate is published. Nothing is done if the manifests, lockfiles,
+/// changelogs or cargo configuration of the workspace have changed since the plan was
+/// made. If a crate fails to publish, the error that the promise rejects with has a
+/// `report` property with the outcome for each crate.
+#[napi]
+pub fn apply(
+    plan: ReleasePlan,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<PublishReport>> {
+    let context = context.unwrap_or_default();
+
+    StepTask::with_report(
+        move || {
+            internal_apply(
+                context.manifest_path().as_deref(),
+                &plan,
+                &context.context(),
+            )
+        },
+        |err| err.publish_report().cloned(),
+    )
+}
+
+/// Create an annotated git tag for each publishable crate of the workspace.
+///
+/// Each tag points at `HEAD` of the workspace of `context` and is named by the tag
+/// format (by default `{name}-v{version}`) with the name and current version of the
+/// crate. Tags that already point at `HEAD` are kept, and nothing is tagged if any tag
+/// already points at another commit. The registry of `opts` defaults to the one in
+/// `context`.
+#[napi]
+pub fn tag(opts: Option<TagArgs>, context: Option<StepOptions>) -> AsyncTask<StepTask<TagReport>> {
+    let context = context.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+
+    StepTask::new(move || {
+        internal_tag(
+            context.manifest_path().as_deref(),
+            &opts,
+            &context.context(),
+        )
+    })
+}
+
+/// Yank a version of the publishable crates of the workspace.
+///
+/// This rolls back a release that went wrong after some of its crates were
+/// published, running `cargo yank` in the directory and environment of `context`. The
+/// publishable crates are yanked in the reverse of the order in which they are
+/// published, and crates that were never published at `version` are reported as
+/// such. With the `undo` option the version is un-yanked instead. The registry of
+/// `opts` defaults to the one in `context`. If a crate fails to be yanked, the error
+/// that the promise rejects with has a `report` property with the outcome for each
+/// crate.
+#[napi]
+pub fn yank(
+    version: String,
+    opts: Option<YankArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<YankReport>> {
+    let context = context.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+
+    StepTask::with_report(
+        move || {
+            internal_yank(
+                context.manifest_path().as_deref(),
+                &version,
+                &opts,
+                &context.context(),
+            )
+        },
+        |err| err.yank_report().cloned(),
+    )
+}
+
+/// Find the next release version from the conventional commits since the last
+/// release.
+///
+/// The last release is the highest version among the release tags reachable from
+/// `HEAD` of the git repository in the current directory. A breaking change calls for
+/// a major release (a minor release while the major version is 0), a `feat` for a
+/// minor release and a `fix` or `perf` for a patch release. Without a previous
+/// release the next version is 1.0.0. `None` is returned if no commit calls for a
+/// release.
+///
+/// This gives the version that the `analyzeCommits` step of `semantic-release` would
+/// choose, for use without `semantic-release`.
+#[napi]
+pub fn next_version(opts: Option<NextVersionArgs>) -> anyhow::Result<Option<String>> {
+    Ok(crate::next_version(None, &opts.unwrap_or_default())?)
+}
+
+/// Generate release notes for the commits since `last_release`, with a section for
+/// each crate of the workspace.
+///
+/// The commits are those from the `gitHead` of `last_release` to the `gitHead` of
+/// `next_release` (`HEAD` if it has none), or every commit up to the `gitHead` of
+/// `next_release` for the first release. A commit belongs to the crates whose
+/// directories contain the files it touched and to the crate named by its
+/// conventional commit scope. Each crate with a breaking change, feature, bug fix or
+/// performance improvement gets a Markdown section headed by its name and the
+/// version of `next_release`, linked to the published crate when its url is known.
+/// The registry of `opts` defaults to the one in `context`.
+///
+/// This implements the `generateNotes` step for `semantic-release` for a Cargo-based
+/// Rust workspace.
+#[napi]
+pub fn generate_notes(
+    last_release: Option<LastRelease>,
+    next_release: NextRelease,
+    opts: Option<GenerateNotesArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<String>> {
+    let context = context.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+    let range = release_range(last_release.as_ref(), &next_release);
+
+    StepTask::new(move || {
+        internal_generate_notes(
+            context.manifest_path().as_deref(),
+            &range,
+            &next_release.version,
+            &opts,
+            &context.context(),
+        )
+    })
+}
+
+/// The git revision range of the commits from `last_release` to `next_release`.
+fn release_range(last_release: Option<&LastRelease>, next_release: &NextRelease) -> String {
+    let next = next_release.git_head.as_deref().unwrap_or("HEAD");
+
+    match last_release.and_then(|release| release.git_head.as_deref()) {
+        Some(last) => format!("{}..{}", last, next),
+        None => next.to_owned(),
+    }
+}
+
+/// Find the crates of the workspace affected by the changes since the git ref `base`.
+///
+/// A crate is affected if a file in its directory changed since `base` (including
+/// uncommitted changes), or if it depends on an affected crate. The names of the
+/// affected crates can be used as the `packages` of [`PrepareArgs`] and
+/// [`PublishArgs`] to release only those crates.
+///
+/// The workspace is the one of `context`.
+#[napi]
+pub fn affected(
+    base: String,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<AffectedPackages>> {
+    let context = context.unwrap_or_default();
+
+    StepTask::new(move || {
+        internal_affected(
+            context.manifest_path().as_deref(),
+            &base,
+            &context.context(),
+        )
+    })
+}
+
+/// List the crates of the workspace in the order in which they are published.
+///
+/// Each crate is listed with its version, its manifest, whether it would be published
+/// to the registry of `opts` (and if not, why not) and the workspace crates that it
+/// depends on.
+///
+/// The workspace is the one of `context`, whose directory and environment `cargo
+/// metadata` runs in. The registry of `opts` defaults to the one in `context`.
+#[napi]
+pub fn list_packages(
+    opts: Option<ListPackagesArgs>,
+    context: Option<StepOptions>,
+) -> AsyncTask<StepTask<Vec<PackageInfo>>> {
+    let context = context.unwrap_or_default();
+    let mut opts = opts.unwrap_or_default();
+    opts.registry = opts.registry.or_else(|| context.registry.clone());
+
+    StepTask::new(move || {
+        internal_package_list(
+            context.manifest_path(),
+            opts.registry.as_deref(),
+            &context.context(),
+        )
+    })
+}
@@ -0,0 +1,247 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Release notes grouped by the crates of a workspace.
+//!
+//! A commit belongs to the crates whose directories contain the files it touched and
+//! to the crate named by its conventional commit scope. Only the commits that call
+//! for a release (breaking changes, features, bug fixes and performance
+//! improvements) are included in the notes.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+    path::Path,
+};
+
+use guppy::graph::PackageGraph;
+use log::{debug, trace};
+
+use crate::{affected::member_for_path, commits::git, commits::ConventionalCommit, Result};
+
+/// The release of a crate of the workspace, for its heading in the notes.
+#[derive(Debug)]
+pub(crate) struct CrateRelease {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) url: Option<String>,
+    pub(crate) docs_url: Option<String>,
+}
+
+/// A section of the notes of a crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Section {
+    Breaking,
+    Features,
+    BugFixes,
+    Performance,
+}
+
+impl Section {
+    fn of(commit: &ConventionalCommit) -> Option<Self> {
+        if commit.breaking {
+            return Some(Section::Breaking);
+        }
+
+        match commit.typ.to_ascii_lowercase().as_str() {
+            "feat" => Some(Section::Features),
+            "fix" => Some(Section::BugFixes),
+            "perf" => Some(Section::Performance),
+            _ => None,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Section::Breaking => "BREAKING CHANGES",
+            Section::Features => "Features",
+            Section::BugFixes => "Bug Fixes",
+            Section::Performance => "Performance Improvements",
+        }
+    }
+}
+
+/// The entries of the notes, by section.
+type Entries = BTreeSet<(Section, usize, String)>;
+
+/// Generate the release notes for the commits in `range` of the workspace of `graph`.
+///
+/// `releases` gives the crates of the workspace in the order in which their sections
+/// appear. Commits that do not belong to any crate are listed under "Other changes"
+/// at the end of the notes.
+pub(crate) fn release_notes(
+    graph: &PackageGraph,
+    range: &str,
+    releases: &[CrateRelease],
+) -> Result<String> {
+    let (by_crate, other) = entries(graph, range)?;
+
+    let mut notes = String::new();
+    for release in releases {
+        if let Some(entries) = by_crate.get(release.name.as_str()) {
+            let heading = match &release.url {
+                Some(url) => format!("[{} {}]({})", release.name, release.version, url),
+                None => format!("{} {}", release.name, release.version),
+            };
+            render(&mut notes, &heading, release.docs_url.as_deref(), entries);
+        }
+    }
+    if !other.is_empty() {
+        render(&mut notes, "Other changes", None, &other);
+    }
+
+    Ok(notes)
+}
+
+/// Generate the notes of each crate of the workspace of `graph` for the commits in
+/// `range`, without a heading.
+///
+/// Crates without any commits in the notes are left out.
+pub(crate) fn crate_notes(graph: &PackageGraph, range: &str) -> Result<HashMap<String, String>> {
+    let (by_crate, _) = entries(graph, range)?;
+
+    Ok(by_crate
+        .into_iter()
+        .map(|(name, entries)| {
+            let mut notes = String::new();
+            render_sections(&mut notes, &entries);
+            (name.to_owned(), notes.trim_start().to_owned())
+        })
+        .collect())
+}
+
+/// The entries of the notes of each crate for the commits in `range`, and the
+/// entries of the commits that do not belong to any crate.
+fn entries<'g>(
+    graph: &'g PackageGraph,
+    range: &str,
+) -> Result<(HashMap<&'g str, Entries>, Entries)> {
+    let workspace = graph.workspace();
+    let root = workspace.root().as_std_path();
+    let members: Vec<_> = workspace
+        .iter_by_path()
+        .map(|(dir, pkg)| (dir.as_std_path(), pkg.name()))
+        .collect();
+
+    let log = git(
+        root,
+        &[
+            "log",
+            "--format=%x1e%H%x1f%B%x1f",
+            "--name-only",
+            "--relative",
+            "--no-renames",
+            range,
+            "--",
+        ],
+    )?;
+
+    let mut by_crate: HashMap<&str, Entries> = HashMap::new();
+    let mut other = Entries::new();
+    for (index, record) in log.split('\x1e').skip(1).enumerate() {
+        let mut fields = record.splitn(3, '\x1f');
+        let (hash, message, paths) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(hash), Some(message), Some(paths)) => (hash, message.trim(), paths),
+            _ => continue,
+        };
+        let Some(commit) = ConventionalCommit::parse(message) else {
+            continue;
+        };
+        let Some(section) = Section::of(&commit) else {
+            continue;
+        };
+
+        let mut crates: BTreeSet<&str> = paths
+            .lines()
+            .filter(|path| !path.is_empty())
+            .filter_map(|path| member_for_path(&members, Path::new(path)))
+            .collect();
+        if let Some(scope) = commit.scope {
+            crates.extend(
+                members
+                    .iter()
+                    .map(|(_, name)| *name)
+                    .find(|name| *name == scope),
+            );
+        }
+        trace!("commit {} belongs to {:?}", hash, crates);
+
+        let entry = (section, index, entry(&commit, hash));
+        if crates.is_empty() {
+            other.insert(entry);
+        } else {
+            for name in crates {
+                by_crate.entry(name).or_default().insert(entry.clone());
+            }
+        }
+    }
+    debug!("release notes for {} crates", by_crate.len());
+
+    Ok((by_crate, other))
+}
+
+/// The entry for `commit` in the notes.
+fn entry(commit: &ConventionalCommit, hash: &str) -> String {
+    let short_hash = hash.get(..7).unwrap_or(hash);
+    match commit.scope {
+        Some(scope) => format!("* **{}:** {} ({})", scope, commit.subject, short_hash),
+        None => format!("* {} ({})", commit.subject, short_hash),
+    }
+}
+
+/// Render the section of a crate, with its entries grouped by section.
+fn render(notes: &mut String, heading: &str, docs_url: Option<&str>, entries: &Entries) {
+    if !notes.is_empty() {
+        notes.push('\n');
+    }
+    let _ = writeln!(notes, "## {}", heading);
+    if let Some(docs_url) = docs_url {
+        let _ = writeln!(notes, "\n[Documentation]({})", docs_url);
+    }
+    render_sections(notes, entries);
+}
+
+/// Render `entries` grouped by section, each section preceded by a blank line.
+fn render_sections(notes: &mut String, entries: &Entries) {
+    let mut current = None;
+    for (section, _, entry) in entries {
+        if current != Some(*section) {
+            let _ = writeln!(notes, "\n### {}\n", section.title());
+            current = Some(*section);
+        }
+        let _ = writeln!(notes, "{}", entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_groups_entries_by_section() {
+        let entries: Entries = [
+            (Section::BugFixes, 0, "* a fix (1234567)".to_owned()),
+            (
+                Section::Features,
+                2,
+                "* **cli:** a flag (89abcde)".to_owned(),
+            ),
+            (Section::Features, 1, "* a feature (fedcba9)".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let mut notes = String::new();
+
+        render(&mut notes, "dep1 2.0.0", None, &entries);
+
+        assert_eq!(
+            notes,
+            "## dep1 2.0.0\n\n### Features\n\n* a feature (fedcba9)\n* **cli:** a flag (89abcde)\n\n### Bug Fixes\n\n* a fix (1234567)\n"
+        );
+    }
+}
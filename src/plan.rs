@@ -0,0 +1,195 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A release plan that records everything a release will do before it is done.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "napi-rs")]
+use napi_derive::napi;
+
+/// A plan for releasing the crates of a workspace.
+///
+/// The plan records the crates of the workspace, the order in which they will be
+/// published, and the exact edits and `cargo publish` commands of the release, so
+/// that it can be reviewed before it is applied.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleasePlan {
+    /// The version to which the crates of the workspace will be set.
+    pub next_version: String,
+
+    /// The registry to which the crates will be published.
+    pub registry: String,
+
+    /// A SHA-256 fingerprint of the files of the workspace that the plan depends on
+    /// when the plan was made: the manifests and lockfiles, the changelogs and the
+    /// cargo configuration.
+    pub fingerprint: String,
+
+    /// The crates of the workspace in dependency order.
+    pub crates: Vec<PlannedCrate>,
+
+    /// The names of the crates to publish, grouped into levels that only depend on
+    /// crates in earlier levels.
+    pub publish_order: Vec<Vec<String>>,
+
+    /// The edits to the manifests and lockfiles of the workspace.
+    pub edits: Vec<PlannedEdit>,
+
+    /// A unified diff of the edits, for review.
+    pub diff: String,
+
+    /// The `cargo publish` commands, in the order in which they will be run.
+    pub commands: Vec<PlannedPublish>,
+
+    /// The name of the main crate of the release, if there is one.
+    pub main_crate: Option<String>,
+
+    /// The longest time, in seconds, that each `cargo publish` command may take, if
+    /// it is limited.
+    pub timeout: Option<u32>,
+
+    /// The maximum number of `cargo publish` commands of a level to run at the same
+    /// time (defaults to 1).
+    pub jobs: Option<u32>,
+
+    /// The format of the annotated git tags to create for the published crates once
+    /// every crate is published, if they are tagged.
+    pub tag_format: Option<String>,
+}
+
+/// A crate of the workspace in a [`ReleasePlan`].
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedCrate {
+    /// The name of the crate.
+    pub name: String,
+
+    /// The current version of the crate.
+    pub version: String,
+
+    /// The version of the crate after the release.
+    pub next_version: String,
+
+    /// Whether the crate will be published.
+    pub publish: bool,
+}
+
+/// The new contents of a file in a [`ReleasePlan`].
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedEdit {
+    /// The path of the file relative to the root of the workspace.
+    pub path: String,
+
+    /// Whether the file is a manifest, a lockfile or a changelog.
+    pub kind: EditKind,
+
+    /// The new contents of the file.
+    pub contents: String,
+}
+
+/// The kind of file edited by a [`PlannedEdit`].
+#[cfg_attr(feature = "napi-rs", napi(string_enum = "lowercase"))]
+#[cfg_attr(not(feature = "napi-rs"), derive(Clone, Copy))]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditKind {
+    /// A `Cargo.toml` file.
+    Manifest,
+
+    /// A `Cargo.lock` file.
+    Lockfile,
+
+    /// The changelog of a crate.
+    Changelog,
+}
+
+/// A `cargo publish` command in a [`ReleasePlan`].
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedPublish {
+    /// The name of the crate to publish.
+    pub name: String,
+
+    /// The version of the crate to publish.
+    pub version: String,
+
+    /// The manifest of the crate relative to the root of the workspace.
+    pub manifest_path: String,
+
+    /// The arguments to `cargo`, which is run from the root of the workspace.
+    pub args: Vec<String>,
+
+    /// The url of the published crate on its registry, if it is known.
+    pub url: Option<String>,
+
+    /// The docs.rs url of the published crate, if docs.rs links are enabled.
+    pub docs_url: Option<String>,
+}
+
+/// A fingerprint of a set of files given as `(path, contents)` pairs.
+///
+/// The fingerprint is the SHA-256 digest of the files in path order, each given by
+/// the length and bytes of its path and then of its contents, written as 64 hex
+/// digits.
+pub(crate) fn fingerprint<'a>(files: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    let mut files: Vec<_> = files.into_iter().collect();
+    files.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for (path, contents) in files {
+        for field in [path, contents] {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_of_nothing_is_sha256_of_nothing() {
+        assert_eq!(
+            fingerprint(None),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn fingerprint_ignores_file_order_but_not_contents() {
+        let a = fingerprint([("Cargo.toml", "a"), ("dep1/Cargo.toml", "b")]);
+        let b = fingerprint([("dep1/Cargo.toml", "b"), ("Cargo.toml", "a")]);
+        let c = fingerprint([("Cargo.toml", "a"), ("dep1/Cargo.toml", "c")]);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn fingerprint_separates_paths_from_contents() {
+        let a = fingerprint([("Cargo.toml", "ab")]);
+        let b = fingerprint([("Cargo.tomla", "b")]);
+
+        assert_ne!(a, b);
+    }
+}
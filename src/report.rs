@@ -0,0 +1,403 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The reports of the outcome of publishing, yanking or tagging a workspace, and of
+//! the crates that a release would publish.
+
+use std::{fmt::Write, time::Duration};
+
+use serde::Serialize;
+
+#[cfg(feature = "napi-rs")]
+use napi_derive::napi;
+
+/// The maximum number of lines of `cargo publish` stderr kept in a [`CrateReport`].
+const STDERR_EXCERPT_LINES: usize = 20;
+
+/// The report of publishing the crates of a workspace.
+///
+/// The `name` and `url` fields form the release record for `semantic-release` so the
+/// report can be returned as is from the `publish` step.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishReport {
+    /// The name of the release.
+    pub name: String,
+
+    /// The url of the main crate of the release, if there is one.
+    pub url: Option<String>,
+
+    /// The docs.rs url of the main crate of the release, if docs.rs links are enabled.
+    pub docs_url: Option<String>,
+
+    /// The registry to which the crates were published.
+    pub registry: String,
+
+    /// The crates of the workspace in the order in which they were considered for
+    /// publication.
+    pub crates: Vec<CrateReport>,
+}
+
+/// The outcome of publishing a single crate.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrateReport {
+    /// The name of the crate.
+    pub name: String,
+
+    /// The version of the crate.
+    pub version: String,
+
+    /// The registry to which the crate was (or would have been) published.
+    pub registry: String,
+
+    /// Whether the crate was published.
+    pub status: PublishStatus,
+
+    /// How long `cargo publish` ran for the crate, in milliseconds.
+    pub duration_ms: u32,
+
+    /// The last lines of the stderr output of `cargo publish` for the crate.
+    pub stderr: Option<String>,
+
+    /// The url of the published crate on its registry, if it is known.
+    pub url: Option<String>,
+
+    /// The docs.rs url of the published crate, if docs.rs links are enabled.
+    pub docs_url: Option<String>,
+}
+
+/// The publication status of a crate.
+#[cfg_attr(feature = "napi-rs", napi(string_enum = "lowercase"))]
+#[cfg_attr(not(feature = "napi-rs"), derive(Clone, Copy))]
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PublishStatus {
+    /// The crate was published.
+    Published,
+
+    /// The crate was not published, either because it is not publishable to the
+    /// target registry or because an earlier crate failed to publish.
+    Skipped,
+
+    /// `cargo publish` failed for the crate.
+    Failed,
+}
+
+/// The report of yanking (or un-yanking) a version of the crates of a workspace.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YankReport {
+    /// The version that was yanked.
+    pub version: String,
+
+    /// The registry from which the version was yanked.
+    pub registry: String,
+
+    /// Whether the version was un-yanked instead of yanked.
+    pub undo: bool,
+
+    /// The publishable crates of the workspace in the order in which they were yanked.
+    pub crates: Vec<YankedCrate>,
+}
+
+/// The outcome of yanking a version of a single crate.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YankedCrate {
+    /// The name of the crate.
+    pub name: String,
+
+    /// Whether the version of the crate was yanked.
+    pub status: YankStatus,
+
+    /// The last lines of the stderr output of `cargo yank` for the crate.
+    pub stderr: Option<String>,
+}
+
+/// The yank status of a crate.
+#[cfg_attr(feature = "napi-rs", napi(string_enum = "lowercase"))]
+#[cfg_attr(not(feature = "napi-rs"), derive(Clone, Copy))]
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum YankStatus {
+    /// The version of the crate was yanked.
+    Yanked,
+
+    /// The version of the crate was un-yanked.
+    Unyanked,
+
+    /// The crate was never published at the version.
+    Unpublished,
+
+    /// `cargo yank` failed for the crate.
+    Failed,
+}
+
+/// The report of tagging the released crates of a workspace.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagReport {
+    /// The commit that the tags point at.
+    pub commit: String,
+
+    /// The tags of the crates in the order in which they are published.
+    pub tags: Vec<CrateTag>,
+}
+
+/// The tag of a single crate.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrateTag {
+    /// The name of the crate.
+    pub name: String,
+
+    /// The version of the crate.
+    pub version: String,
+
+    /// The name of the tag.
+    pub tag: String,
+
+    /// Whether the tag was created.
+    pub status: TagStatus,
+}
+
+/// The status of the tag of a crate.
+#[cfg_attr(feature = "napi-rs", napi(string_enum = "lowercase"))]
+#[cfg_attr(not(feature = "napi-rs"), derive(Clone, Copy))]
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagStatus {
+    /// The tag was created.
+    Created,
+
+    /// The tag already pointed at the commit.
+    Existing,
+}
+
+/// The report of a release that failed.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailReport {
+    /// The path of the journal of the interrupted publish, if there is one.
+    pub journal: Option<String>,
+
+    /// The path of the file to which this report, with its hint to finish or roll back
+    /// the release, was written.
+    pub rollback: String,
+
+    /// The registry to which the crates were being published, if it is known.
+    pub registry: Option<String>,
+
+    /// The crates that were published before the release failed.
+    pub published: Vec<PublishedCrate>,
+
+    /// How to finish or roll back the release.
+    pub hint: String,
+}
+
+/// A crate published by a release that failed.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedCrate {
+    /// The name of the crate.
+    pub name: String,
+
+    /// The version of the crate.
+    pub version: String,
+}
+
+/// A crate of the workspace as it would be considered for a release.
+#[cfg_attr(feature = "napi-rs", napi(object))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageInfo {
+    /// The name of the crate.
+    pub name: String,
+
+    /// The current version of the crate.
+    pub version: String,
+
+    /// The path to the `Cargo.toml` file of the crate.
+    pub manifest_path: String,
+
+    /// Whether the crate would be published to the target registry.
+    pub publishable: bool,
+
+    /// Why the crate would not be published, if it would not.
+    pub reason: Option<String>,
+
+    /// The workspace crates that the crate depends on, other than through
+    /// `dev-dependencies`.
+    pub dependencies: Vec<String>,
+}
+
+impl PublishReport {
+    /// The crates of the report that were published.
+    pub fn published(&self) -> impl Iterator<Item = &CrateReport> {
+        self.crates
+            .iter()
+            .filter(|report| report.status == PublishStatus::Published)
+    }
+
+    /// A summary of the release, with a line for each published crate.
+    pub fn summary(&self) -> String {
+        let published: Vec<_> = self.published().collect();
+        let mut summary = format!(
+            "Published {} {} to {}",
+            published.len(),
+            if published.len() == 1 {
+                "crate"
+            } else {
+                "crates"
+            },
+            self.registry
+        );
+        for report in &published {
+            let _ = write!(summary, "\n* {} {}", report.name, report.version);
+            if let Some(url) = &report.url {
+                let _ = write!(summary, " ({})", url);
+            }
+        }
+
+        let failed: Vec<_> = self
+            .crates
+            .iter()
+            .filter(|report| report.status == PublishStatus::Failed)
+            .map(|report| report.name.as_str())
+            .collect();
+        if !failed.is_empty() {
+            let _ = write!(summary, "\nFailed to publish {}", failed.join(", "));
+        }
+
+        summary
+    }
+}
+
+impl CrateReport {
+    pub(crate) fn new(
+        name: impl Into<String>,
+        version: impl Into<String>,
+        registry: impl Into<String>,
+        status: PublishStatus,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            registry: registry.into(),
+            status,
+            duration_ms: 0,
+            stderr: None,
+            url: None,
+            docs_url: None,
+        }
+    }
+
+    pub(crate) fn duration(mut self, duration: Duration) -> Self {
+        self.duration_ms = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+        self
+    }
+
+    pub(crate) fn stderr(mut self, stderr: &[u8]) -> Self {
+        self.stderr = stderr_excerpt(&String::from_utf8_lossy(stderr));
+        self
+    }
+
+    pub(crate) fn url(mut self, url: Option<String>) -> Self {
+        self.url = url;
+        self
+    }
+
+    pub(crate) fn docs_url(mut self, docs_url: Option<String>) -> Self {
+        self.docs_url = docs_url;
+        self
+    }
+}
+
+impl YankedCrate {
+    pub(crate) fn new(name: impl Into<String>, status: YankStatus, stderr: &[u8]) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            stderr: stderr_excerpt(&String::from_utf8_lossy(stderr)),
+        }
+    }
+}
+
+fn stderr_excerpt(stderr: &str) -> Option<String> {
+    let lines: Vec<_> = stderr.trim_end().lines().collect();
+    let start = lines.len().saturating_sub(STDERR_EXCERPT_LINES);
+    let excerpt = lines[start..].join("\n");
+
+    if excerpt.is_empty() {
+        None
+    } else {
+        Some(excerpt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stderr_excerpt_keeps_the_last_lines() {
+        let stderr: Vec<_> = (0..30).map(|i| format!("line {}", i)).collect();
+
+        let excerpt = stderr_excerpt(&stderr.join("\n")).expect("no excerpt");
+
+        assert_eq!(excerpt.lines().count(), STDERR_EXCERPT_LINES);
+        assert_eq!(excerpt.lines().next(), Some("line 10"));
+        assert_eq!(excerpt.lines().last(), Some("line 29"));
+    }
+
+    #[test]
+    fn stderr_excerpt_of_empty_output_is_none() {
+        assert_eq!(stderr_excerpt("\n"), None);
+    }
+
+    #[test]
+    fn crate_report_serializes_status_in_lowercase() {
+        let report = CrateReport::new("dep1", "2.0.0", "crates.io", PublishStatus::Skipped);
+
+        let json = serde_json::to_value(&report).expect("unable to serialize report");
+
+        assert_eq!(json["status"], "skipped");
+        assert_eq!(json["durationMs"], 0);
+        assert!(json["url"].is_null());
+    }
+
+    #[test]
+    fn summary_lists_published_crates() {
+        let report = PublishReport {
+            name: "crates.io packages (1 packages published)".to_owned(),
+            url: None,
+            docs_url: None,
+            registry: "crates.io".to_owned(),
+            crates: vec![
+                CrateReport::new("dep1", "2.0.0", "crates.io", PublishStatus::Published)
+                    .url(Some("https://crates.io/crates/dep1/2.0.0".to_owned())),
+                CrateReport::new("build1", "2.0.0", "crates.io", PublishStatus::Failed),
+                CrateReport::new("main", "2.0.0", "crates.io", PublishStatus::Skipped),
+            ],
+        };
+
+        assert_eq!(
+            report.summary(),
+            "Published 1 crate to crates.io\n* dep1 2.0.0 (https://crates.io/crates/dep1/2.0.0)\nFailed to publish build1"
+        );
+    }
+}
@@ -0,0 +1,404 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The running of `cargo` commands.
+//!
+//! The release steps run `cargo publish`, `cargo yank`, `cargo info` and
+//! `cargo metadata` through a [`CargoRunner`], which can also run `cargo package`.
+//! The default runner starts a `cargo` child process, passing on its output line by
+//! line as it is written and killing it if it runs for too long.
+//! Another runner can run a wrapper such as `cross` instead, or record the commands
+//! without running them.
+
+use std::{
+    collections::HashMap,
+    env, fmt,
+    io::{self, BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How often a child process whose output has closed is checked for having exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A `cargo` command to run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CargoInvocation {
+    /// The crate that the command is for, if it is for a single crate.
+    pub package: Option<String>,
+
+    /// The arguments to `cargo`, starting with the subcommand.
+    pub args: Vec<String>,
+
+    /// The directory in which to run the command, if not the current directory.
+    pub current_dir: Option<PathBuf>,
+
+    /// Variables to add to, or override in, the environment of the command.
+    pub env: HashMap<String, String>,
+
+    /// The longest time that the command may run, if it is limited.
+    ///
+    /// A runner that stops the command once this has passed fails with an
+    /// [`io::ErrorKind::TimedOut`] error.
+    pub timeout: Option<Duration>,
+}
+
+/// The output stream of a command from which a line of output came.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    /// The standard output of the command.
+    Stdout,
+
+    /// The standard error of the command.
+    Stderr,
+}
+
+/// A way to run the `cargo` commands of the release steps.
+///
+/// Only [`run`](CargoRunner::run) is required. The other methods are called for the
+/// subcommand that they are named for, and run the command with `run` unless they are
+/// overridden.
+pub trait CargoRunner: fmt::Debug + Send + Sync {
+    /// Run a `cargo` command and collect its output.
+    fn run(&self, invocation: &CargoInvocation) -> io::Result<Output>;
+
+    /// Run `cargo publish` for a single crate.
+    ///
+    /// Each line of the output, without its line ending, is passed to `on_line`. The
+    /// lines are passed once the command has finished unless this is overridden to
+    /// pass them as they are written.
+    fn publish(
+        &self,
+        invocation: &CargoInvocation,
+        on_line: &mut dyn FnMut(OutputStream, &str),
+    ) -> io::Result<Output> {
+        let output = self.run(invocation)?;
+        for line in output.stdout.lines() {
+            on_line(OutputStream::Stdout, &line?);
+        }
+        for line in output.stderr.lines() {
+            on_line(OutputStream::Stderr, &line?);
+        }
+
+        Ok(output)
+    }
+
+    /// Run `cargo package` for a single crate.
+    ///
+    /// The release steps do not package crates themselves, since `cargo publish`
+    /// does; this is for callers that check a crate with `cargo package` through the
+    /// same runner before publishing it.
+    fn package(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.run(invocation)
+    }
+
+    /// Run `cargo yank` for a single crate.
+    fn yank(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.run(invocation)
+    }
+
+    /// Run `cargo info` for a version of a single crate.
+    ///
+    /// The standard output describes the version that was found, in a line starting
+    /// with `version: `.
+    fn info(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.run(invocation)
+    }
+
+    /// Run `cargo metadata` for the workspace.
+    ///
+    /// The standard output is the JSON description of the workspace.
+    fn metadata(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.run(invocation)
+    }
+}
+
+/// The default [`CargoRunner`], which runs each command as a child process.
+///
+/// A command with a timeout is killed once the timeout has passed.
+#[derive(Debug, Default, Clone)]
+pub struct ProcessCargoRunner {
+    program: Option<PathBuf>,
+}
+
+impl ProcessCargoRunner {
+    /// A runner for the `cargo` executable given by `$CARGO`, or found on the `PATH`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A runner for the executable `program` (such as `cross`) in place of `cargo`.
+    pub fn with_program(program: impl Into<PathBuf>) -> Self {
+        ProcessCargoRunner {
+            program: Some(program.into()),
+        }
+    }
+
+    /// The child process for `invocation`.
+    pub fn command(&self, invocation: &CargoInvocation) -> Command {
+        let program = self
+            .program
+            .clone()
+            .unwrap_or_else(|| cargo_path(&invocation.env));
+
+        let mut command = Command::new(program);
+        command.args(&invocation.args).envs(&invocation.env);
+        if let Some(dir) = invocation.current_dir.as_deref() {
+            command.current_dir(dir);
+        }
+
+        command
+    }
+
+    /// Run the child process for `invocation`, passing each line of its output to
+    /// `on_line` as it is written.
+    fn run_streaming(
+        &self,
+        invocation: &CargoInvocation,
+        on_line: &mut dyn FnMut(OutputStream, &str),
+    ) -> io::Result<Output> {
+        let mut child = self
+            .command(invocation)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let (sender, receiver) = mpsc::channel();
+        let readers = [
+            forward_lines(child.stdout.take(), OutputStream::Stdout, sender.clone()),
+            forward_lines(child.stderr.take(), OutputStream::Stderr, sender),
+        ];
+        let deadline = invocation
+            .timeout
+            .map(|timeout| (Instant::now() + timeout, timeout));
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        loop {
+            let received = match deadline {
+                Some((at, timeout)) => {
+                    match receiver.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                        Err(RecvTimeoutError::Timeout) => return Err(kill(child, timeout)),
+                        received => received,
+                    }
+                }
+                None => receiver.recv().map_err(RecvTimeoutError::from),
+            };
+            let Ok((stream, line)) = received else {
+                break;
+            };
+
+            on_line(
+                stream,
+                String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']),
+            );
+            match stream {
+                OutputStream::Stdout => stdout.extend(line),
+                OutputStream::Stderr => stderr.extend(line),
+            }
+        }
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            match deadline {
+                Some((at, timeout)) if Instant::now() >= at => return Err(kill(child, timeout)),
+                Some(_) => thread::sleep(EXIT_POLL_INTERVAL),
+                None => break child.wait()?,
+            }
+        };
+
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl CargoRunner for ProcessCargoRunner {
+    fn run(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.run_streaming(invocation, &mut |_, _| {})
+    }
+
+    fn publish(
+        &self,
+        invocation: &CargoInvocation,
+        on_line: &mut dyn FnMut(OutputStream, &str),
+    ) -> io::Result<Output> {
+        self.run_streaming(invocation, on_line)
+    }
+}
+
+/// A [`CargoRunner`] that records the commands instead of running them.
+///
+/// Each command succeeds with no output unless it is for a crate named with
+/// [`fail_for`](RecordingCargoRunner::fail_for) or
+/// [`fail_with`](RecordingCargoRunner::fail_with), and `cargo info` finds only the
+/// versions named with [`published`](RecordingCargoRunner::published). The
+/// `cargo publish`, `cargo package`, `cargo yank` and `cargo info` commands are
+/// recorded. The `cargo metadata` commands are not recorded and are run as child
+/// processes, so that the workspace can still be read; a workspace built with
+/// [`Workspace::from_metadata_json_with_runner`](crate::Workspace::from_metadata_json_with_runner)
+/// runs them only to load the workspace again after it is edited.
+#[derive(Debug, Default)]
+pub struct RecordingCargoRunner {
+    invocations: Mutex<Vec<CargoInvocation>>,
+    failures: HashMap<String, String>,
+    published: HashMap<String, String>,
+    metadata: ProcessCargoRunner,
+}
+
+impl RecordingCargoRunner {
+    /// A runner for which every recorded command succeeds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail the commands for the crate `package`, with an exit code of 1.
+    pub fn fail_for(self, package: impl Into<String>) -> Self {
+        self.fail_with(package, "error: recorded failure")
+    }
+
+    /// Fail the commands for the crate `package`, with an exit code of 1 and `stderr`
+    /// as the standard error.
+    pub fn fail_with(mut self, package: impl Into<String>, stderr: impl Into<String>) -> Self {
+        self.failures.insert(package.into(), stderr.into());
+        self
+    }
+
+    /// Have `cargo info` find `version` of the crate `package`.
+    pub fn published(mut self, package: impl Into<String>, version: impl Into<String>) -> Self {
+        self.published.insert(package.into(), version.into());
+        self
+    }
+
+    /// The recorded commands in the order in which they were run.
+    pub fn invocations(&self) -> Vec<CargoInvocation> {
+        self.invocations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+impl CargoRunner for RecordingCargoRunner {
+    fn run(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.invocations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(invocation.clone());
+
+        let failure = invocation
+            .package
+            .as_ref()
+            .and_then(|package| self.failures.get(package));
+        let (status, stderr) = match failure {
+            Some(stderr) => (failure_status(), format!("{}\n", stderr).into_bytes()),
+            None => (ExitStatus::default(), Vec::new()),
+        };
+
+        Ok(Output {
+            status,
+            stdout: Vec::new(),
+            stderr,
+        })
+    }
+
+    fn info(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        let mut output = self.run(invocation)?;
+        let published = invocation
+            .package
+            .as_ref()
+            .and_then(|package| self.published.get(package));
+        if let Some(version) = published.filter(|_| output.status.success()) {
+            output.stdout = format!("version: {}\n", version).into_bytes();
+        }
+
+        Ok(output)
+    }
+
+    fn metadata(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.metadata.run(invocation)
+    }
+}
+
+/// Send each line read from `pipe`, with its line ending, to `sender` from a new thread.
+fn forward_lines(
+    pipe: Option<impl Read + Send + 'static>,
+    stream: OutputStream,
+    sender: Sender<(OutputStream, Vec<u8>)>,
+) -> Option<JoinHandle<()>> {
+    let pipe = pipe?;
+
+    Some(thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        loop {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {
+                    if sender.send((stream, line)).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Kill a child process that ran for longer than `timeout`, giving the error for
+/// that.
+///
+/// The threads reading its output are left to finish when the output closes, which
+/// may be later if the child started processes of its own.
+fn kill(mut child: Child, timeout: Duration) -> io::Error {
+    let _ = child.kill();
+    let _ = child.wait();
+
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("cargo did not finish within {} seconds", timeout.as_secs()),
+    )
+}
+
+/// The `cargo` executable, from `$CARGO` in `env` or else in the environment of the
+/// process.
+fn cargo_path(env: &HashMap<String, String>) -> PathBuf {
+    env.get("CARGO")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("CARGO").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("cargo"))
+}
+
+/// The status of a process that exited with a code of 1.
+#[cfg(unix)]
+fn failure_status() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    ExitStatus::from_raw(1 << 8)
+}
+
+/// The status of a process that exited with a code of 1.
+#[cfg(windows)]
+fn failure_status() -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+
+    ExitStatus::from_raw(1)
+}
@@ -0,0 +1,122 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Annotated git tags for each released crate.
+
+use std::path::Path;
+
+use log::{debug, info};
+
+use crate::{
+    commits::git,
+    report::{CrateTag, TagReport, TagStatus},
+    Error, Result,
+};
+
+/// The format of the tag of a crate unless another one is configured.
+pub(crate) const DEFAULT_TAG_FORMAT: &str = "{name}-v{version}";
+
+/// Check that `format` gives a different tag for each version of a crate.
+pub(crate) fn validate_format(format: &str) -> Result<()> {
+    if format.contains("{version}") {
+        Ok(())
+    } else {
+        Err(Error::tag_format_error(format))
+    }
+}
+
+/// The tag of version `version` of the crate `name` in `format`.
+pub(crate) fn tag_name(format: &str, name: &str, version: &str) -> String {
+    format.replace("{name}", name).replace("{version}", version)
+}
+
+/// Create an annotated tag, pointing at `HEAD` of the git repository at
+/// `repository`, for each of the `(name, version)` crates.
+///
+/// Tags that already point at `HEAD` are kept. If any tag points at another commit
+/// then no tags are created.
+pub(crate) fn create_tags(
+    repository: &Path,
+    crates: &[(String, String)],
+    format: &str,
+) -> Result<TagReport> {
+    validate_format(format)?;
+    let commit = git(repository, &["rev-parse", "HEAD^{commit}"])?
+        .trim()
+        .to_owned();
+
+    let mut tags = Vec::with_capacity(crates.len());
+    for (name, version) in crates {
+        let tag = tag_name(format, name, version);
+        let status = match tagged_commit(repository, &tag)? {
+            Some(tagged) if tagged == commit => TagStatus::Existing,
+            Some(tagged) => return Err(Error::tag_conflict(tag, tagged)),
+            None => TagStatus::Created,
+        };
+        tags.push(CrateTag {
+            name: name.clone(),
+            version: version.clone(),
+            tag,
+            status,
+        });
+    }
+
+    for tag in &tags {
+        match tag.status {
+            TagStatus::Created => {
+                info!("Creating tag {}", tag.tag);
+                let message = format!("{} {}", tag.name, tag.version);
+                git(
+                    repository,
+                    &[
+                        "tag",
+                        "--annotate",
+                        "--message",
+                        &message,
+                        &tag.tag,
+                        &commit,
+                    ],
+                )?;
+            }
+            TagStatus::Existing => debug!("tag {} already points at {}", tag.tag, commit),
+        }
+    }
+
+    Ok(TagReport { commit, tags })
+}
+
+/// The commit that `tag` points at, if the tag exists.
+fn tagged_commit(repository: &Path, tag: &str) -> Result<Option<String>> {
+    let reference = format!("refs/tags/{}", tag);
+    if git(repository, &["tag", "--list", tag])?.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let commit = git(
+        repository,
+        &["rev-parse", &format!("{}^{{commit}}", reference)],
+    )?;
+    Ok(Some(commit.trim().to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_name_replaces_placeholders() {
+        assert_eq!(tag_name(DEFAULT_TAG_FORMAT, "dep1", "2.0.0"), "dep1-v2.0.0");
+        assert_eq!(tag_name("v{version}", "dep1", "2.0.0"), "v2.0.0");
+    }
+
+    #[test]
+    fn format_without_version_is_error() {
+        assert!(validate_format("{name}").is_err());
+        assert!(validate_format("{name}@{version}").is_ok());
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A set of file edits that are written together or not at all.
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, error};
+use similar::TextDiff;
+
+use crate::{Error, Result};
+
+/// A set of staged file edits.
+///
+/// Nothing is written until [`Transaction::commit`] is called. Each file is then
+/// written atomically and, if any write fails, the files already written are restored
+/// to their original contents.
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    edits: Vec<Edit>,
+}
+
+#[derive(Debug)]
+struct Edit {
+    path: PathBuf,
+    original: String,
+    contents: String,
+    created: bool,
+}
+
+impl Transaction {
+    /// Stage new `contents` for the file at `path`, which currently holds `original`.
+    pub(crate) fn stage(
+        &mut self,
+        path: impl Into<PathBuf>,
+        original: String,
+        contents: impl Into<String>,
+    ) {
+        self.edits.push(Edit {
+            path: path.into(),
+            original,
+            contents: contents.into(),
+            created: false,
+        });
+    }
+
+    /// Stage the creation of the file at `path` with `contents`.
+    ///
+    /// The file is removed again if the transaction fails.
+    pub(crate) fn stage_new(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.edits.push(Edit {
+            path: path.into(),
+            original: String::new(),
+            contents: contents.into(),
+            created: true,
+        });
+    }
+
+    /// The staged edits as `(path, original, contents)` triples.
+    pub(crate) fn edits(&self) -> impl Iterator<Item = (&Path, &str, &str)> {
+        self.edits.iter().map(|edit| {
+            (
+                edit.path.as_path(),
+                edit.original.as_str(),
+                edit.contents.as_str(),
+            )
+        })
+    }
+
+    /// The paths of the files that the staged edits create or change.
+    pub(crate) fn changed_paths(&self) -> Vec<PathBuf> {
+        self.edits
+            .iter()
+            .filter(|edit| edit.created || edit.original != edit.contents)
+            .map(|edit| edit.path.clone())
+            .collect()
+    }
+
+    /// A unified diff of the staged edits, with paths shown relative to `root`.
+    ///
+    /// The diff is empty if none of the edits changes its file.
+    pub(crate) fn diff(&self, root: &Path) -> String {
+        self.edits
+            .iter()
+            .filter(|edit| edit.created || edit.original != edit.contents)
+            .map(|edit| {
+                let path = edit.path.strip_prefix(root).unwrap_or(&edit.path);
+                let original = if edit.created {
+                    "/dev/null".to_owned()
+                } else {
+                    format!("a/{}", path.display())
+                };
+                TextDiff::from_lines(&edit.original, &edit.contents)
+                    .unified_diff()
+                    .header(&original, &format!("b/{}", path.display()))
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// Write all of the staged edits, restoring the original files on failure.
+    pub(crate) fn commit(self) -> Result<()> {
+        self.commit_with(write_atomic, |path| fs::remove_file(path))
+    }
+
+    fn commit_with<W, R>(self, mut write: W, mut remove: R) -> Result<()>
+    where
+        W: FnMut(&Path, &str) -> io::Result<()>,
+        R: FnMut(&Path) -> io::Result<()>,
+    {
+        for (index, edit) in self.edits.iter().enumerate() {
+            if !edit.created && edit.original == edit.contents {
+                continue;
+            }
+
+            debug!("writing {}", edit.path.display());
+            if let Err(err) = write(&edit.path, &edit.contents) {
+                error!(
+                    "Unable to write {}, restoring the files already written",
+                    edit.path.display()
+                );
+                for written in self.edits[..index].iter().rev() {
+                    if !written.created && written.original == written.contents {
+                        continue;
+                    }
+
+                    debug!("restoring {}", written.path.display());
+                    let restored = if written.created {
+                        remove(&written.path)
+                    } else {
+                        write(&written.path, &written.original)
+                    };
+                    if let Err(restore_err) = restored {
+                        error!(
+                            "Unable to restore {}: {}",
+                            written.path.display(),
+                            restore_err
+                        );
+                    }
+                }
+
+                return Err(Error::file_write_error(err, &edit.path));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Write `contents` to `path` by writing a sibling temporary file and renaming it over
+/// `path`, so that `path` is never left partially written.
+///
+/// If `path` is a symlink then the file that it links to is replaced, and the
+/// replaced file keeps its permissions.
+pub(crate) fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let (path, permissions) = match fs::canonicalize(path) {
+        Ok(target) => {
+            let permissions = fs::metadata(&target)?.permissions();
+            (target, Some(permissions))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => (path.to_path_buf(), None),
+        Err(err) => return Err(err),
+    };
+
+    let mut file_name = OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(".semantic-release-cargo.tmp");
+    let temp_path = path.with_file_name(file_name);
+
+    fs::write(&temp_path, contents)
+        .and_then(|()| match permissions {
+            Some(permissions) => fs::set_permissions(&temp_path, permissions),
+            None => Ok(()),
+        })
+        .and_then(|()| fs::rename(&temp_path, &path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn staged(files: &[(&str, &str, &str)]) -> Transaction {
+        let mut transaction = Transaction::default();
+        for (path, original, contents) in files {
+            transaction.stage(*path, (*original).to_owned(), *contents);
+        }
+        transaction
+    }
+
+    #[test]
+    fn commit_writes_changed_files_only() {
+        let transaction = staged(&[("a", "1", "2"), ("b", "1", "1"), ("c", "1", "3")]);
+        let mut files = HashMap::new();
+
+        transaction
+            .commit_with(
+                |path, contents| {
+                    files.insert(path.to_owned(), contents.to_owned());
+                    Ok(())
+                },
+                |_| Ok(()),
+            )
+            .expect("commit failed");
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[Path::new("a")], "2");
+        assert_eq!(files[Path::new("c")], "3");
+    }
+
+    #[test]
+    fn diff_shows_changed_files_relative_to_root() {
+        let transaction = staged(&[
+            (
+                "/ws/Cargo.toml",
+                "version = \"1.0.0\"\n",
+                "version = \"2.0.0\"\n",
+            ),
+            (
+                "/ws/dep1/Cargo.toml",
+                "version = \"2.0.0\"\n",
+                "version = \"2.0.0\"\n",
+            ),
+        ]);
+
+        let diff = transaction.diff(Path::new("/ws"));
+
+        assert!(diff.starts_with("--- a/Cargo.toml\n+++ b/Cargo.toml\n"));
+        assert!(diff.contains("-version = \"1.0.0\"\n+version = \"2.0.0\"\n"));
+        assert!(!diff.contains("dep1"));
+    }
+
+    #[test]
+    fn failed_write_restores_files_already_written() {
+        let transaction = staged(&[("a", "a1", "a2"), ("b", "b1", "b2"), ("c", "c1", "c2")]);
+        let mut files: HashMap<PathBuf, String> = HashMap::new();
+
+        let result = transaction.commit_with(
+            |path, contents| {
+                if path == Path::new("b") && contents == "b2" {
+                    return Err(io::Error::other("injected failure"));
+                }
+                files.insert(path.to_owned(), contents.to_owned());
+                Ok(())
+            },
+            |_| Ok(()),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[Path::new("a")], "a1");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().expect("Couldn't create temp dir");
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, "version = \"1.0.0\"\n").expect("Couldn't write the file");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .expect("Couldn't set the permissions");
+
+        write_atomic(&path, "version = \"2.0.0\"\n").expect("write_atomic failed");
+
+        let mode = fs::metadata(&path)
+            .expect("no metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(
+            fs::read_to_string(&path).expect("Couldn't read the file"),
+            "version = \"2.0.0\"\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_replaces_the_target_of_a_symlink() {
+        let dir = tempfile::tempdir().expect("Couldn't create temp dir");
+        let target = dir.path().join("shared.toml");
+        let link = dir.path().join("Cargo.toml");
+        fs::write(&target, "version = \"1.0.0\"\n").expect("Couldn't write the file");
+        std::os::unix::fs::symlink(&target, &link).expect("Couldn't create the symlink");
+
+        write_atomic(&link, "version = \"2.0.0\"\n").expect("write_atomic failed");
+
+        let link_type = fs::symlink_metadata(&link)
+            .expect("no metadata")
+            .file_type();
+        assert!(link_type.is_symlink());
+        assert_eq!(
+            fs::read_to_string(&target).expect("Couldn't read the file"),
+            "version = \"2.0.0\"\n"
+        );
+    }
+
+    #[test]
+    fn failed_write_removes_files_already_created() {
+        let mut transaction = staged(&[("a", "a1", "a2")]);
+        transaction.stage_new("CHANGELOG.md", "## 2.0.0\n");
+        transaction.stage("c", "c1".to_owned(), "c2");
+        let files = std::cell::RefCell::new(HashMap::<PathBuf, String>::new());
+
+        let result = transaction.commit_with(
+            |path, contents| {
+                if path == Path::new("c") {
+                    return Err(io::Error::other("injected failure"));
+                }
+                files
+                    .borrow_mut()
+                    .insert(path.to_owned(), contents.to_owned());
+                Ok(())
+            },
+            |path| {
+                files.borrow_mut().remove(path);
+                Ok(())
+            },
+        );
+
+        assert!(result.is_err());
+        assert_eq!(files.borrow().len(), 1);
+        assert_eq!(files.borrow()[Path::new("a")], "a1");
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A workspace loaded once for a sequence of release steps.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use guppy::graph::PackageGraph;
+use log::warn;
+
+use crate::{
+    affected_workspace, apply_plan, check_registry_token, config::WorkspaceConfig,
+    context::StepContext, get_package_graph_in, package_infos, plan_workspace,
+    prepare_diff_workspace, prepare_workspace, publish_workspace, tag_workspace,
+    verify_release_workspace, verify_workspace, yank_workspace, AffectedPackages, CargoRunner,
+    Error, ListPackagesArgs, PackageInfo, PrepareArgs, ProcessCargoRunner, PublishArgs,
+    PublishReport, ReleasePlan, Result, TagArgs, TagReport, VerifyArgs, VerifyReleaseArgs,
+    YankArgs, YankReport,
+};
+
+/// The stand-in for the manifest path in the errors of a workspace built from JSON.
+const METADATA_JSON: &str = "<cargo metadata JSON>";
+
+/// A Cargo workspace loaded for a release.
+///
+/// The package graph and the release configuration in the workspace metadata are read
+/// once when the workspace is loaded, and the cargo configuration when it is first
+/// needed, so that a sequence of steps runs `cargo metadata` only once. The cargo
+/// commands are run by a [`CargoRunner`], which starts a `cargo` child process unless
+/// the workspace is loaded with [`with_runner`](Workspace::with_runner).
+#[derive(Debug)]
+pub struct Workspace {
+    graph: PackageGraph,
+    config: WorkspaceConfig,
+    cargo_config: OnceLock<cargo_config2::Config>,
+    context: StepContext,
+}
+
+impl Workspace {
+    /// Load the workspace whose root `Cargo.toml` file is at `manifest_path`.
+    ///
+    /// If `manifest_path` is `None` then the root of the workspace is found from the
+    /// `Cargo.toml` file in the current directory.
+    pub fn new(manifest_path: Option<&Path>) -> Result<Self> {
+        Self::load(manifest_path, &StepContext::default())
+    }
+
+    /// Load the workspace whose root `Cargo.toml` file is at `manifest_path`, running
+    /// the cargo commands, including the `cargo metadata` that loads it, with `runner`.
+    pub fn with_runner(manifest_path: Option<&Path>, runner: Arc<dyn CargoRunner>) -> Result<Self> {
+        Self::load(manifest_path, &StepContext::default().with_runner(runner))
+    }
+
+    /// Build the workspace from the JSON output of `cargo metadata`.
+    ///
+    /// No cargo command is run to build the workspace. The steps read and write the
+    /// files named in the metadata, and run cargo in the root directory of the
+    /// workspace. The steps that edit the manifests, [`prepare`](Workspace::prepare)
+    /// and [`apply`](Workspace::apply), then load the workspace with
+    /// `cargo metadata`, since the JSON no longer describes it.
+    pub fn from_metadata_json(json: &str) -> Result<Self> {
+        Self::from_metadata_json_with_runner(json, Arc::new(ProcessCargoRunner::new()))
+    }
+
+    /// Build the workspace from the JSON output of `cargo metadata`, running the cargo
+    /// commands of the steps with `runner`.
+    ///
+    /// Together with a [`RecordingCargoRunner`](crate::RecordingCargoRunner) this
+    /// runs the steps without running cargo at all, except for the `cargo metadata`
+    /// that loads the workspace again after [`prepare`](Workspace::prepare) or
+    /// [`apply`](Workspace::apply).
+    pub fn from_metadata_json_with_runner(
+        json: &str,
+        runner: Arc<dyn CargoRunner>,
+    ) -> Result<Self> {
+        let graph = PackageGraph::from_json(json)
+            .map_err(|err| Error::workspace_error(err, PathBuf::from(METADATA_JSON)))?;
+        let root = graph.workspace().root().as_std_path().to_path_buf();
+        let context = StepContext::new(Some(root), HashMap::new()).with_runner(runner);
+
+        Self::with_graph(graph, context)
+    }
+
+    /// Load the workspace at `manifest_path` in the directory and environment of
+    /// `context`.
+    pub(crate) fn load(manifest_path: Option<&Path>, context: &StepContext) -> Result<Self> {
+        let graph = get_package_graph_in(manifest_path, context)?;
+
+        Self::with_graph(graph, context.clone())
+    }
+
+    fn with_graph(graph: PackageGraph, context: StepContext) -> Result<Self> {
+        let config = WorkspaceConfig::from_graph(&graph)?;
+
+        Ok(Workspace {
+            graph,
+            config,
+            cargo_config: OnceLock::new(),
+            context,
+        })
+    }
+
+    /// The root directory of the workspace.
+    pub fn root(&self) -> &Path {
+        self.graph.workspace().root().as_std_path()
+    }
+
+    /// The crates of the workspace in the order in which they are published.
+    ///
+    /// Each crate is listed with its version, its manifest, whether it would be
+    /// published to the registry of `opts` (and if not, why not) and the workspace
+    /// crates that it depends on.
+    pub fn packages(&self, opts: &ListPackagesArgs) -> Vec<PackageInfo> {
+        package_infos(&self.graph, opts.registry.as_deref())
+    }
+
+    /// Verify that the conditions for a release are satisfied.
+    ///
+    /// The conditions are those checked by
+    /// [`verify_conditions_with_args`](crate::verify_conditions_with_args).
+    pub fn verify(&self, args: &VerifyArgs) -> Result<()> {
+        check_registry_token(self.cargo_config()?, args.registry.as_deref())?;
+
+        verify_workspace(&self.graph, &self.config, args)
+    }
+
+    /// Prepare the workspace for a release.
+    ///
+    /// This sets the versions of the crates selected by `opts` as described for
+    /// [`prepare`](crate::prepare). The workspace is then loaded again so that the
+    /// later steps see the new versions. Loading it runs `cargo metadata` with the
+    /// runner of the workspace, even for a workspace built from metadata JSON.
+    pub fn prepare(&mut self, next_release_version: &str, opts: &PrepareArgs) -> Result<()> {
+        prepare_workspace(&self.graph, &self.config, next_release_version, opts)?;
+        self.reload()
+    }
+
+    /// Compute the changes that preparing the workspace for a release would make.
+    ///
+    /// This returns the unified diff described for
+    /// [`prepare_diff`](crate::prepare_diff), without writing anything.
+    pub fn prepare_diff(&self, next_release_version: &str, opts: &PrepareArgs) -> Result<String> {
+        prepare_diff_workspace(&self.graph, next_release_version, opts)
+    }
+
+    /// Plan a release of the workspace without changing anything.
+    ///
+    /// The plan is the one described for [`plan`](crate::plan).
+    pub fn plan(
+        &self,
+        next_release_version: &str,
+        prepare_opts: &PrepareArgs,
+        opts: &PublishArgs,
+    ) -> Result<ReleasePlan> {
+        plan_workspace(
+            &self.graph,
+            &self.config,
+            next_release_version,
+            prepare_opts,
+            opts,
+        )
+    }
+
+    /// Verify that `next_release_version` can be released from the workspace.
+    ///
+    /// The version is checked against the crates selected by `opts`, and looked up
+    /// with `cargo info`, as described for [`verify_release`](crate::verify_release).
+    pub fn verify_release(
+        &self,
+        next_release_version: &str,
+        opts: &VerifyReleaseArgs,
+    ) -> Result<()> {
+        verify_release_workspace(&self.graph, next_release_version, opts, &self.context)
+    }
+
+    /// Publish the publishable crates of the workspace.
+    ///
+    /// This runs `cargo publish` for the crates selected by `opts` as described for
+    /// [`publish`](crate::publish).
+    pub fn publish(&self, opts: &PublishArgs) -> Result<PublishReport> {
+        publish_workspace(&self.graph, &self.config, opts, &self.context)
+    }
+
+    /// Apply a release plan to the workspace.
+    ///
+    /// This writes the edits of `plan` and runs its `cargo publish` commands as
+    /// described for [`apply`](crate::apply), and then loads the workspace again, as
+    /// [`prepare`](Workspace::prepare) does. If the plan fails to apply its error is
+    /// returned, with the report of the crates, even if the workspace then fails to
+    /// load.
+    pub fn apply(&mut self, plan: &ReleasePlan) -> Result<PublishReport> {
+        let applied = apply_plan(&self.graph, plan, &self.context);
+        let reloaded = self.reload();
+
+        match (applied, reloaded) {
+            (Ok(report), reloaded) => reloaded.map(|()| report),
+            (Err(err), Err(reload_err)) => {
+                warn!(
+                    "Unable to load the workspace again after the plan failed: {}",
+                    reload_err
+                );
+                Err(err)
+            }
+            (Err(err), Ok(())) => Err(err),
+        }
+    }
+
+    /// Create an annotated git tag for each publishable crate of the workspace.
+    ///
+    /// The tags are those described for [`tag`](crate::tag).
+    pub fn tag(&self, opts: &TagArgs) -> Result<TagReport> {
+        tag_workspace(&self.graph, &self.config, opts)
+    }
+
+    /// Find the crates of the workspace affected by the changes since the git ref
+    /// `base`, as described for [`affected`](crate::affected).
+    pub fn affected(&self, base: &str) -> Result<AffectedPackages> {
+        affected_workspace(&self.graph, base)
+    }
+
+    /// Yank a version of the publishable crates of the workspace.
+    ///
+    /// This runs `cargo yank` for the crates as described for [`yank`](crate::yank).
+    pub fn yank(&self, version: &str, opts: &YankArgs) -> Result<YankReport> {
+        yank_workspace(&self.graph, version, opts, &self.context)
+    }
+
+    /// Read the package graph and the release configuration again after the
+    /// manifests have been edited.
+    fn reload(&mut self) -> Result<()> {
+        let manifest_path = self.root().join("Cargo.toml");
+        self.graph = get_package_graph_in(Some(manifest_path), &self.context)?;
+        self.config = WorkspaceConfig::from_graph(&self.graph)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn graph(&self) -> &PackageGraph {
+        &self.graph
+    }
+
+    pub(crate) fn config(&self) -> &WorkspaceConfig {
+        &self.config
+    }
+
+    /// The cargo configuration for the workspace, which is loaded on first use.
+    fn cargo_config(&self) -> Result<&cargo_config2::Config> {
+        if let Some(cargo_config) = self.cargo_config.get() {
+            return Ok(cargo_config);
+        }
+        let cargo_config = self.context.cargo_config()?;
+
+        Ok(self.cargo_config.get_or_init(|| cargo_config))
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::affected;
+
+#[test]
+fn affected_includes_dependents_of_changed_crates() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let root = manifest.parent().unwrap();
+    fs::write(root.join("dep1/src/lib.rs"), "// changed\n").expect("Couldn't write file");
+
+    let affected = affected(Some(&manifest), "HEAD").expect("affected failed");
+
+    assert_eq!(affected.base, "HEAD");
+    assert_eq!(affected.changed, vec!["dep1"]);
+    assert_eq!(affected.affected, vec!["dep1", "dependencies"]);
+}
+
+#[test]
+fn affected_root_change_does_not_affect_members() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let root = manifest.parent().unwrap();
+    fs::write(root.join("src/lib.rs"), "// changed\n").expect("Couldn't write file");
+
+    let affected = affected(Some(&manifest), "HEAD").expect("affected failed");
+
+    assert_eq!(affected.changed, vec!["dependencies"]);
+    assert_eq!(affected.affected, vec!["dependencies"]);
+}
+
+#[test]
+fn affected_ignores_dev_dependents() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies_with_explicit_version");
+    let root = manifest.parent().unwrap();
+    fs::write(root.join("dev1/src/lib.rs"), "// changed\n").expect("Couldn't write file");
+
+    let affected = affected(Some(&manifest), "HEAD").expect("affected failed");
+
+    assert_eq!(affected.affected, vec!["dev1"]);
+}
+
+#[test]
+fn affected_without_changes_is_empty() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+
+    let affected = affected(Some(&manifest), "HEAD").expect("affected failed");
+
+    assert!(affected.changed.is_empty());
+    assert!(affected.affected.is_empty());
+}
+
+#[test]
+fn affected_with_unknown_base_is_error() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+
+    assert!(affected(Some(&manifest), "no-such-ref").is_err());
+}
+
+/// Copy a test workspace into a new git repository with a single commit.
+fn copy_workspace_repo(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let root = tempdir.path().join(workspace);
+    git(&root, &["init", "--quiet"]);
+    git(&root, &["add", "."]);
+    git(&root, &["commit", "--quiet", "-m", "initial"]);
+
+    (tempdir, root.join("Cargo.toml"))
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .status()
+        .expect("Couldn't run git");
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
@@ -0,0 +1,65 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The Rust API of the library is the same with and without the `napi-rs` feature.
+//!
+//! This test only compiles if each public function has the signature given here, so
+//! running the tests with and without the feature checks both configurations.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use semantic_release_cargo::{
+    affected, apply, fail, generate_notes, list_packages, list_packages_with_arguments,
+    next_version, package_list, plan, prepare, prepare_diff, prepare_with_args, publish, tag,
+    verify_conditions, verify_conditions_with_alternate, verify_conditions_with_args,
+    verify_release, yank, AffectedPackages, CargoRunner, FailReport, GenerateNotesArgs,
+    ListPackagesArgs, NextVersionArgs, PackageInfo, PrepareArgs, PublishArgs, PublishReport,
+    ReleasePlan, Result, TagArgs, TagReport, VerifyArgs, VerifyReleaseArgs, Workspace, YankArgs,
+    YankReport,
+};
+
+#[test]
+fn public_functions_have_stable_signatures() {
+    let _: fn(Option<&Path>) -> Result<()> = |path| verify_conditions(path);
+    let _: fn(Option<&str>, Option<&Path>) -> Result<()> =
+        |registry, path| verify_conditions_with_alternate(registry, path);
+    let _: fn(Option<&Path>, &VerifyArgs) -> Result<()> =
+        |path, args| verify_conditions_with_args(path, args);
+    let _: fn(Option<&Path>, &str, &VerifyReleaseArgs) -> Result<()> = verify_release;
+    let _: fn(Option<&Path>, String) -> Result<()> = prepare;
+    let _: fn(Option<&Path>, String, &PrepareArgs) -> Result<()> = prepare_with_args;
+    let _: fn(Option<&Path>, &str, &PrepareArgs) -> Result<String> = prepare_diff;
+    let _: fn(Option<&Path>, &PublishArgs) -> Result<PublishReport> = publish;
+    let _: fn(Option<&Path>) -> Result<FailReport> = fail;
+    let _: fn(Option<&Path>, &str, &PrepareArgs, &PublishArgs) -> Result<ReleasePlan> = plan;
+    let _: fn(Option<&Path>, &ReleasePlan) -> Result<PublishReport> = apply;
+    let _: fn(Option<&Path>, &TagArgs) -> Result<TagReport> = tag;
+    let _: fn(Option<&Path>, &str, &YankArgs) -> Result<YankReport> = yank;
+    let _: fn(Option<&Path>, &NextVersionArgs) -> Result<Option<String>> = next_version;
+    let _: fn(Option<&Path>, &str, &str, &GenerateNotesArgs) -> Result<String> = generate_notes;
+    let _: fn(Option<&Path>, &str) -> Result<AffectedPackages> = affected;
+    let _: fn(Option<&Path>) -> Result<()> = |path| list_packages(path);
+    let _: fn(Option<&str>, Option<&Path>) -> Result<()> =
+        |registry, path| list_packages_with_arguments(registry, path);
+    let _: fn(Option<&Path>, &ListPackagesArgs) -> Result<Vec<PackageInfo>> =
+        |path, opts| package_list(path, opts);
+}
+
+#[test]
+fn workspace_methods_have_stable_signatures() {
+    let _: fn(Option<&Path>) -> Result<Workspace> = Workspace::new;
+    let _: fn(Option<&Path>, Arc<dyn CargoRunner>) -> Result<Workspace> = Workspace::with_runner;
+    let _: fn(&str) -> Result<Workspace> = Workspace::from_metadata_json;
+    let _: fn(&Workspace) -> &Path = Workspace::root;
+    let _: fn(&Workspace, &ListPackagesArgs) -> Vec<PackageInfo> = Workspace::packages;
+    let _: fn(&Workspace, &VerifyArgs) -> Result<()> = Workspace::verify;
+    let _: fn(&mut Workspace, &str, &PrepareArgs) -> Result<()> = Workspace::prepare;
+    let _: fn(&Workspace, &PublishArgs) -> Result<PublishReport> = Workspace::publish;
+    let _: fn(&Workspace, &str, &YankArgs) -> Result<YankReport> = Workspace::yank;
+}
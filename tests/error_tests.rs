@@ -6,42 +6,54 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::path::PathBuf;
+use std::env;
+use std::path::{Path, PathBuf};
 
-use semantic_release_cargo::Error;
+use assert_matches::assert_matches;
+use url::ParseError;
+
+use semantic_release_cargo::{verify_conditions, Error, Workspace};
 
 #[test]
-fn error_format_includes_stderr() {
-    // Create a path
-    let path = PathBuf::from("Cargo.toml");
-
-    // Create an error with stderr message by using the public constructor
-    let error = Error::CargoPublishStatus {
-        status: dummy_exit_status(),
-        manifest_path: path,
-        stderr: "Error: failed to publish\nCause: package already exists on registry".to_string(),
+fn workspace_error_names_the_manifest() {
+    let path = get_test_data_manifest_path("missing");
+
+    let result = Workspace::new(Some(&path));
+
+    let Err(Error::WorkspaceError(err)) = result else {
+        panic!("loading a missing workspace did not fail with a workspace error");
     };
+    assert_eq!(err.manifest_path(), path);
+    assert!(!err.metadata_error().to_string().is_empty());
+}
+
+#[test]
+fn url_error_gives_the_parse_error() {
+    env::set_var("CARGO_REGISTRY_TOKEN", "fake_token");
+    let path = get_test_data_manifest_path("invalid_url_template");
+
+    let result = verify_conditions(Some(&path));
+
+    let Err(Error::UrlError(err)) = result else {
+        panic!("verifying an invalid url template did not fail with a url error");
+    };
+    assert_eq!(err.parse_error(), ParseError::RelativeUrlWithoutBase);
+}
+
+#[test]
+fn returned_error_has_a_code() {
+    let path = get_test_data_manifest_path("missing");
+
+    let result = Workspace::new(Some(&path));
 
-    // Check that the formatted error message includes the stderr content
-    let error_string = format!("{}", error);
-    assert!(
-        error_string.contains("failed to publish"),
-        "Error should contain stderr content"
-    );
-    assert!(
-        error_string.contains("package already exists"),
-        "Error should contain all stderr content"
-    );
+    assert_matches!(result, Err(ref err) if err.code() == "EWORKSPACE");
 }
 
-fn dummy_exit_status() -> std::process::ExitStatus {
-    use std::process::Command;
+fn get_test_data_manifest_path(workspace: impl AsRef<Path>) -> PathBuf {
+    let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
 
-    // Create a real exit status with code 101
-    if cfg!(windows) {
-        Command::new("cmd").args(["/C", "exit 101"]).status()
-    } else {
-        Command::new("sh").args(["-c", "exit 101"]).status()
-    }
-    .expect("Failed to execute command")
+    Path::new(cargo_manifest_dir)
+        .join("test_data")
+        .join(workspace)
+        .join("Cargo.toml")
 }
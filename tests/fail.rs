@@ -0,0 +1,94 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::fail;
+
+#[test]
+fn fail_reports_crates_in_the_journal() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let journal = journal_path(&manifest);
+    fs::create_dir_all(journal.parent().unwrap()).expect("Couldn't create journal dir");
+    fs::write(
+        &journal,
+        r#"{"registry":"internal","published":[{"name":"dep1","version":"2.0.0"}]}"#,
+    )
+    .expect("Couldn't write journal");
+
+    let report = fail(Some(&manifest)).expect("fail failed");
+
+    assert_eq!(report.journal.as_deref(), journal.to_str());
+    let rollback = journal.with_file_name("rollback.json");
+    assert_eq!(report.rollback, rollback.to_str().unwrap());
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&rollback).expect("no rollback file"))
+            .expect("rollback file is not JSON");
+    assert_eq!(written["hint"], report.hint.as_str());
+    assert_eq!(written["published"][0]["name"], "dep1");
+    assert!(journal.exists());
+    assert_eq!(report.registry.as_deref(), Some("internal"));
+    assert_eq!(report.published.len(), 1);
+    assert_eq!(report.published[0].name, "dep1");
+    assert!(report.hint.contains("publish --resume --registry internal"));
+    assert!(report
+        .hint
+        .contains("semantic-release-cargo yank --registry internal 2.0.0"));
+}
+
+#[test]
+fn fail_without_journal_has_nothing_to_roll_back() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+
+    let report = fail(Some(&manifest)).expect("fail failed");
+
+    assert_eq!(report.journal, None);
+    assert!(report.published.is_empty());
+    assert!(report.hint.contains("nothing to roll back"));
+    let rollback = journal_path(&manifest).with_file_name("rollback.json");
+    assert_eq!(report.rollback, rollback.to_str().unwrap());
+    let written = fs::read_to_string(&rollback).expect("no rollback file");
+    assert!(written.contains("nothing to roll back"));
+    assert!(!journal_path(&manifest).exists());
+}
+
+fn journal_path(manifest: &Path) -> PathBuf {
+    manifest
+        .parent()
+        .unwrap()
+        .join("target")
+        .join("semantic-release-cargo")
+        .join("journal.json")
+}
+
+fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let mut cargo_toml = tempdir.path().join(workspace);
+    cargo_toml.push("Cargo.toml");
+
+    (tempdir, cargo_toml)
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
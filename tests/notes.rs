@@ -0,0 +1,135 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{generate_notes, GenerateNotesArgs};
+
+#[test]
+fn notes_are_grouped_by_crate() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let root = manifest.parent().unwrap();
+    commit_file(root, "dep1/src/lib.rs", "feat: a dep1 feature");
+    commit_file(root, "build1/src/lib.rs", "fix(build): a build1 fix");
+    commit_file(root, "dep1/src/lib.rs", "docs: not in the notes");
+
+    let notes = generate_notes(
+        Some(&manifest),
+        "HEAD~3..HEAD",
+        "2.0.0",
+        &GenerateNotesArgs::default(),
+    )
+    .expect("generate_notes failed");
+
+    let dep1 = notes
+        .find("## [dep1 2.0.0](https://crates.io/crates/dep1/2.0.0)")
+        .expect("no dep1 section");
+    let build1 = notes
+        .find("## [build1 2.0.0](https://crates.io/crates/build1/2.0.0)")
+        .expect("no build1 section");
+    assert!(notes[dep1..].contains("### Features\n\n* a dep1 feature ("));
+    assert!(notes[build1..].contains("### Bug Fixes\n\n* **build:** a build1 fix ("));
+    assert!(!notes.contains("not in the notes"));
+    assert!(!notes.contains("## [dependencies"));
+}
+
+#[test]
+fn notes_attribute_commits_by_scope() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let root = manifest.parent().unwrap();
+    commit_file(root, "src/lib.rs", "feat(dep1)!: a breaking change");
+
+    let notes = generate_notes(
+        Some(&manifest),
+        "HEAD~1..HEAD",
+        "2.0.0",
+        &GenerateNotesArgs {
+            url_template: Some("https://crates.internal/{name}/{version}".into()),
+            ..Default::default()
+        },
+    )
+    .expect("generate_notes failed");
+
+    assert!(notes.contains("## [dep1 2.0.0](https://crates.internal/dep1/2.0.0)"));
+    assert!(notes.contains("## [dependencies 2.0.0](https://crates.internal/dependencies/2.0.0)"));
+    assert!(notes.contains("### BREAKING CHANGES\n\n* **dep1:** a breaking change ("));
+}
+
+#[test]
+fn notes_without_releasable_commits_are_empty() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let root = manifest.parent().unwrap();
+    commit_file(root, "dep1/src/lib.rs", "chore: tidy");
+
+    let notes = generate_notes(
+        Some(&manifest),
+        "HEAD~1..HEAD",
+        "2.0.0",
+        &GenerateNotesArgs::default(),
+    )
+    .expect("generate_notes failed");
+
+    assert_eq!(notes, "");
+}
+
+/// Copy a test workspace into a new git repository with a single commit.
+fn copy_workspace_repo(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let root = tempdir.path().join(workspace);
+    git(&root, &["init", "--quiet"]);
+    git(&root, &["add", "."]);
+    git(&root, &["commit", "--quiet", "-m", "initial"]);
+
+    (tempdir, root.join("Cargo.toml"))
+}
+
+/// Append to a file of the repository and commit the change.
+fn commit_file(root: &Path, path: &str, message: &str) {
+    let path = root.join(path);
+    let contents = fs::read_to_string(&path).expect("Couldn't read file");
+    fs::write(&path, contents + "// changed\n").expect("Couldn't write file");
+    git(root, &["commit", "--quiet", "-a", "-m", message]);
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .status()
+        .expect("Couldn't run git");
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
@@ -0,0 +1,101 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::path::{Path, PathBuf};
+
+use semantic_release_cargo::{package_list, ListPackagesArgs};
+
+#[test]
+fn package_list_is_in_publish_order() {
+    let path = get_test_data_manifest_path("dependencies");
+
+    let packages =
+        package_list(Some(path), &ListPackagesArgs::default()).expect("unable to list packages");
+
+    let names: Vec<_> = packages.iter().map(|pkg| pkg.name.as_str()).collect();
+    assert_eq!(names.len(), 3);
+    assert_eq!(names[2], "dependencies");
+    assert!(packages
+        .iter()
+        .all(|pkg| pkg.publishable && pkg.reason.is_none()));
+
+    let root = &packages[2];
+    assert_eq!(root.version, "0.1.0");
+    assert!(root.manifest_path.ends_with("dependencies/Cargo.toml"));
+    let mut dependencies = root.dependencies.clone();
+    dependencies.sort_unstable();
+    assert_eq!(dependencies, vec!["build1", "dep1"]);
+}
+
+#[test]
+fn package_list_gives_reason_for_restricted_crate() {
+    std::env::set_var(
+        "CARGO_REGISTRIES_TEST_INDEX",
+        "https://github.com/rust-lang/crates.io-index",
+    );
+    let path = get_test_data_manifest_path("dependencies_alternate_registry");
+
+    let packages =
+        package_list(Some(&path), &ListPackagesArgs::default()).expect("unable to list packages");
+    let root = packages.last().expect("no packages");
+
+    assert_eq!(root.name, "dependencies_alt_registry");
+    assert!(!root.publishable);
+    assert_eq!(root.reason.as_deref(), Some("not published to crates-io"));
+
+    let args = ListPackagesArgs {
+        registry: Some("test".into()),
+    };
+    let packages = package_list(Some(&path), &args).expect("unable to list packages");
+    assert!(packages.iter().all(|pkg| pkg.publishable));
+}
+
+#[test]
+fn package_list_checks_each_registry_of_publish_list() {
+    let path = get_test_data_manifest_path("multiple_registries");
+
+    let packages =
+        package_list(Some(&path), &ListPackagesArgs::default()).expect("unable to list packages");
+
+    assert_eq!(packages.len(), 1);
+    assert!(!packages[0].publishable);
+    assert_eq!(
+        packages[0].reason.as_deref(),
+        Some("not published to crates-io")
+    );
+
+    for registry in ["reg-a", "reg-b"] {
+        let args = ListPackagesArgs {
+            registry: Some(registry.into()),
+        };
+        let packages = package_list(Some(&path), &args).expect("unable to list packages");
+        assert!(packages[0].publishable, "not publishable to {}", registry);
+        assert_eq!(packages[0].reason, None);
+    }
+
+    let args = ListPackagesArgs {
+        registry: Some("reg-c".into()),
+    };
+    let packages = package_list(Some(&path), &args).expect("unable to list packages");
+    assert_eq!(
+        packages[0].reason.as_deref(),
+        Some("not published to reg-c")
+    );
+}
+
+fn get_test_data_manifest_path(dir: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(dir);
+    path.push("Cargo.toml");
+
+    path
+}
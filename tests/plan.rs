@@ -0,0 +1,432 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{
+    apply, plan, CargoInvocation, CargoRunner, EditKind, Error, OutputStream, PrepareArgs,
+    PublishArgs, PublishStatus, RecordingCargoRunner, ReleasePlan, Workspace,
+};
+
+#[test]
+fn plan_records_release_without_writing() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let before = read_workspace_files(tempdir.path());
+
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+    assert_eq!(plan.next_version, "2.0.0");
+    assert_eq!(plan.registry, "crates.io");
+    assert_eq!(plan.crates.len(), 3);
+    assert!(plan.crates.iter().all(|c| c.next_version == "2.0.0"));
+    assert_eq!(plan.publish_order.len(), 2);
+    assert_eq!(plan.publish_order[1], vec!["dependencies"]);
+    assert_eq!(plan.main_crate.as_deref(), Some("dependencies"));
+    assert!(plan
+        .edits
+        .iter()
+        .any(|edit| edit.path == "dep1/Cargo.toml" && edit.kind == EditKind::Manifest));
+    assert!(plan.diff.contains("+version = \"2.0.0\""));
+
+    let last = plan.commands.last().expect("no publish commands");
+    assert_eq!(last.name, "dependencies");
+    assert_eq!(
+        last.args,
+        vec!["publish", "--manifest-path", "Cargo.toml", "--allow-dirty"]
+    );
+    assert_eq!(
+        last.url.as_deref(),
+        Some("https://crates.io/crates/dependencies/2.0.0")
+    );
+}
+
+#[test]
+fn plan_selected_packages() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let prepare_args = PrepareArgs {
+        packages: Some(vec!["dep1".into()]),
+        ..Default::default()
+    };
+    let args = PublishArgs {
+        packages: Some(vec!["dep1".into()]),
+        ..Default::default()
+    };
+
+    let plan = plan(Some(&manifest), "2.0.0", &prepare_args, &args).expect("plan failed");
+
+    assert_eq!(plan.publish_order, vec![vec!["dep1"]]);
+    assert_eq!(plan.main_crate.as_deref(), Some("dep1"));
+    let dependencies = plan
+        .crates
+        .iter()
+        .find(|c| c.name == "dependencies")
+        .expect("no root crate");
+    assert_eq!(dependencies.next_version, "0.1.0");
+    assert!(!dependencies.publish);
+}
+
+#[test]
+fn plan_round_trips_through_json() {
+    let (_tempdir, manifest) = copy_workspace("basic");
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+
+    let json = serde_json::to_string(&plan).expect("unable to serialize plan");
+    let parsed: ReleasePlan = serde_json::from_str(&json).expect("unable to parse plan");
+
+    assert_eq!(parsed.fingerprint, plan.fingerprint);
+    assert_eq!(parsed.edits.len(), plan.edits.len());
+    assert_eq!(parsed.commands.len(), plan.commands.len());
+}
+
+#[test]
+fn apply_refuses_changed_workspace() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    let dep1 = manifest.parent().unwrap().join("dep1").join("Cargo.toml");
+    let contents = fs::read_to_string(&dep1).expect("Couldn't read dep1 manifest");
+    fs::write(&dep1, contents + "\n# changed\n").expect("Couldn't write dep1 manifest");
+    let before = read_workspace_files(tempdir.path());
+
+    let result = apply(Some(&manifest), &plan);
+
+    let err = result.expect_err("apply of a stale plan succeeded");
+    assert_matches!(err, Error::PlanMismatch { .. });
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+}
+
+#[test]
+fn apply_refuses_edit_outside_workspace() {
+    let (tempdir, manifest) = copy_workspace("basic");
+    let mut plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    plan.edits[0].path = "../elsewhere/Cargo.toml".into();
+    let before = read_workspace_files(tempdir.path());
+
+    let result = apply(Some(&manifest), &plan);
+
+    assert!(result.is_err());
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+}
+
+#[test]
+fn apply_refuses_changed_changelog() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let changelog = manifest.with_file_name("CHANGELOG.md");
+    fs::write(&changelog, "# Changelog\n").expect("Couldn't write changelog");
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    fs::write(&changelog, "# Changelog\n\n## 2.0.0\n").expect("Couldn't write changelog");
+    let before = read_workspace_files(tempdir.path());
+
+    let result = apply(Some(&manifest), &plan);
+
+    let err = result.expect_err("apply after a changelog change succeeded");
+    assert_matches!(err, Error::PlanMismatch { .. });
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+}
+
+#[test]
+fn plan_records_jobs_and_tags() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let args = PublishArgs {
+        jobs: Some(2),
+        tag: Some(true),
+        tag_format: Some("{name}@{version}".into()),
+        resume: Some(true),
+        ..Default::default()
+    };
+
+    let plan = plan(Some(&manifest), "2.0.0", &PrepareArgs::default(), &args).expect("plan failed");
+
+    assert_eq!(plan.jobs, Some(2));
+    assert_eq!(plan.tag_format.as_deref(), Some("{name}@{version}"));
+}
+
+#[test]
+fn apply_writes_edits_and_runs_publish_commands() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let mut workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let report = workspace.apply(&plan).expect("apply failed");
+
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| c.status == PublishStatus::Published));
+    assert_eq!(
+        report.url.as_deref(),
+        Some("https://crates.io/crates/dependencies/2.0.0")
+    );
+    let invocations = runner.invocations();
+    assert_eq!(invocations.len(), 3);
+    let last = invocations.last().unwrap();
+    assert_eq!(last.package.as_deref(), Some("dependencies"));
+    assert_eq!(
+        last.args,
+        vec!["publish", "--manifest-path", "Cargo.toml", "--allow-dirty"]
+    );
+    let root = fs::read_to_string(&manifest).expect("Couldn't read manifest");
+    assert!(root.contains("version = \"2.0.0\""));
+    assert!(workspace
+        .packages(&Default::default())
+        .iter()
+        .all(|package| package.version == "2.0.0"));
+}
+
+#[test]
+fn plan_records_changelog_edits() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let before = read_workspace_files(tempdir.path());
+    let prepare_args = PrepareArgs {
+        changelog: Some(true),
+        changelog_notes: Some([("dep1".to_owned(), "* a new feature\n".to_owned())].into()),
+        changelog_date: Some("2020-02-02".into()),
+        ..Default::default()
+    };
+
+    let changelog_plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &prepare_args,
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+    let edit = changelog_plan
+        .edits
+        .iter()
+        .find(|edit| edit.path == "dep1/CHANGELOG.md")
+        .expect("no changelog edit");
+    assert_eq!(edit.kind, EditKind::Changelog);
+    assert_eq!(
+        edit.contents,
+        "# Changelog\n\n## 2.0.0 (2020-02-02)\n\n* a new feature\n"
+    );
+    assert!(changelog_plan.diff.contains("+++ b/dep1/CHANGELOG.md"));
+    let without_changelogs = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    assert_eq!(changelog_plan.fingerprint, without_changelogs.fingerprint);
+}
+
+#[test]
+fn apply_writes_changelog_edits() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let root = manifest.parent().unwrap();
+    fs::write(
+        root.join("CHANGELOG.md"),
+        "# Changelog\n\n## 0.1.0 (2020-01-01)\n\n* first release\n",
+    )
+    .expect("Couldn't write changelog");
+    let prepare_args = PrepareArgs {
+        changelog: Some(true),
+        changelog_date: Some("2020-02-02".into()),
+        ..Default::default()
+    };
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &prepare_args,
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let mut workspace = Workspace::with_runner(Some(&manifest), runner).expect("workspace failed");
+
+    workspace.apply(&plan).expect("apply failed");
+
+    assert_eq!(
+        fs::read_to_string(root.join("CHANGELOG.md")).expect("Couldn't read changelog"),
+        "# Changelog\n\n## 2.0.0 (2020-02-02)\n\nNo notable changes.\n\n## 0.1.0 (2020-01-01)\n\n* first release\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("dep1/CHANGELOG.md")).expect("Couldn't read changelog"),
+        "# Changelog\n\n## 2.0.0 (2020-02-02)\n\nNo notable changes.\n"
+    );
+}
+
+#[test]
+fn apply_stops_at_failed_level() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    let runner = Arc::new(RecordingCargoRunner::new().fail_for("dep1"));
+    let mut workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let result = workspace.apply(&plan);
+
+    let err = result.expect_err("apply with a failing crate succeeded");
+    let report = err.publish_report().expect("no publish report");
+    let status = |name: &str| {
+        &report
+            .crates
+            .iter()
+            .find(|c| c.name == name)
+            .expect("crate missing from report")
+            .status
+    };
+    assert_eq!(*status("dep1"), PublishStatus::Failed);
+    assert_eq!(*status("dependencies"), PublishStatus::Skipped);
+    assert!(runner
+        .invocations()
+        .iter()
+        .all(|invocation| invocation.package.as_deref() != Some("dependencies")));
+}
+
+#[test]
+fn failed_apply_keeps_its_error_when_the_workspace_cannot_be_loaded() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let plan = plan(
+        Some(&manifest),
+        "2.0.0",
+        &PrepareArgs::default(),
+        &PublishArgs::default(),
+    )
+    .expect("plan failed");
+    let runner = Arc::new(LoadOnceRunner {
+        loaded: AtomicBool::new(false),
+        recording: RecordingCargoRunner::new().fail_for("dep1"),
+    });
+    let mut workspace = Workspace::with_runner(Some(&manifest), runner).expect("workspace failed");
+
+    let result = workspace.apply(&plan);
+
+    let err = result.expect_err("apply with a failing crate succeeded");
+    assert_matches!(err, Error::PublishFailed { .. });
+    assert!(err.publish_report().is_some());
+}
+
+/// A runner that fails every `cargo metadata` after the first, so that the workspace
+/// can be loaded but not loaded again.
+#[derive(Debug)]
+struct LoadOnceRunner {
+    loaded: AtomicBool,
+    recording: RecordingCargoRunner,
+}
+
+impl CargoRunner for LoadOnceRunner {
+    fn run(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.recording.run(invocation)
+    }
+
+    fn publish(
+        &self,
+        invocation: &CargoInvocation,
+        on_line: &mut dyn FnMut(OutputStream, &str),
+    ) -> io::Result<Output> {
+        self.recording.publish(invocation, on_line)
+    }
+
+    fn metadata(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        if self.loaded.swap(true, Ordering::SeqCst) {
+            return Err(io::Error::other("the workspace was already loaded"));
+        }
+
+        self.recording.metadata(invocation)
+    }
+}
+
+fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let mut cargo_toml = tempdir.path().join(workspace);
+    cargo_toml.push("Cargo.toml");
+
+    (tempdir, cargo_toml)
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
+
+fn read_workspace_files(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).expect("Couldn't read dir") {
+            let path = entry.expect("Couldn't read dir entry").path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                let contents = fs::read_to_string(&path).expect("Couldn't read file");
+                files.push((path, contents));
+            }
+        }
+    }
+    files.sort();
+
+    files
+}
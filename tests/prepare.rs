@@ -9,14 +9,16 @@
 use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
+use assert_matches::assert_matches;
 use fs_extra::dir::{copy, CopyOptions};
 use guppy::{graph::PackageGraph, MetadataCommand};
 use semver::Version;
 use tempfile::{tempdir, TempDir};
 use toml_edit::{DocumentMut, Table};
 
-use semantic_release_cargo::prepare;
+use semantic_release_cargo::{prepare, prepare_diff, prepare_with_args, Error, PrepareArgs};
 
 #[test]
 fn prepare_basic() {
@@ -88,6 +90,157 @@ fn prepare_with_dependencies_with_aliased_package_names() {
     );
 }
 
+#[test]
+fn prepare_selected_packages() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let args = PrepareArgs {
+        packages: Some(vec!["dep1".into()]),
+        ..Default::default()
+    };
+
+    prepare_with_args(Some(&manifest), "2.0.0".into(), &args).expect("prepare failed");
+
+    let graph = get_package_graph(&manifest);
+    let workspace = graph.workspace();
+    let version = |name| workspace.member_by_name(name).unwrap().version().clone();
+    assert_eq!(version("dep1"), Version::new(2, 0, 0));
+    assert_eq!(version("build1"), Version::new(0, 1, 0));
+    assert_eq!(version("dependencies"), Version::new(0, 1, 0));
+    let cargo_toml = get_toml_document(&manifest);
+    let root = cargo_toml.as_table();
+    assert_eq!(get_dep_version(root, "dependencies", "dep1"), "2.0.0");
+}
+
+#[test]
+fn prepare_unknown_package_is_error() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let before = read_workspace_files(tempdir.path());
+    let args = PrepareArgs {
+        packages: Some(vec!["missing".into()]),
+        ..Default::default()
+    };
+
+    let result = prepare_with_args(Some(&manifest), "2.0.0".into(), &args);
+
+    assert!(result.is_err());
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+}
+
+#[test]
+fn prepare_adds_changelog_sections() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let root = manifest.parent().unwrap();
+    fs::write(
+        root.join("dep1/CHANGELOG.md"),
+        "# dep1 changes\n\n## 0.1.0 (2020-01-01)\n\n* first release\n",
+    )
+    .expect("Couldn't write changelog");
+    let args = PrepareArgs {
+        changelog: Some(true),
+        changelog_notes: Some([("dep1".to_owned(), "* a new feature\n".to_owned())].into()),
+        changelog_date: Some("2020-02-02".into()),
+        ..Default::default()
+    };
+
+    prepare_with_args(Some(&manifest), "2.0.0".into(), &args).expect("prepare failed");
+
+    assert_eq!(
+        fs::read_to_string(root.join("dep1/CHANGELOG.md")).expect("Couldn't read changelog"),
+        "# dep1 changes\n\n## 2.0.0 (2020-02-02)\n\n* a new feature\n\n## 0.1.0 (2020-01-01)\n\n* first release\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("CHANGELOG.md")).expect("Couldn't read changelog"),
+        "# Changelog\n\n## 2.0.0 (2020-02-02)\n\nNo notable changes.\n"
+    );
+    let diff = prepare_diff(Some(&manifest), "2.0.0", &args).expect("prepare_diff failed");
+    assert_eq!(diff, "");
+}
+
+#[test]
+fn prepare_changelog_path_from_package_metadata() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let dep1 = manifest.parent().unwrap().join("dep1");
+    let contents = fs::read_to_string(dep1.join("Cargo.toml")).expect("Couldn't read manifest");
+    fs::write(
+        dep1.join("Cargo.toml"),
+        contents + "\n[package.metadata.semantic-release-cargo]\nchangelog = \"CHANGES.md\"\n",
+    )
+    .expect("Couldn't write manifest");
+    let args = PrepareArgs {
+        packages: Some(vec!["dep1".into()]),
+        changelog: Some(true),
+        changelog_date: Some("2020-02-02".into()),
+        ..Default::default()
+    };
+
+    prepare_with_args(Some(&manifest), "2.0.0".into(), &args).expect("prepare failed");
+
+    assert!(dep1.join("CHANGES.md").exists());
+    assert!(!dep1.join("CHANGELOG.md").exists());
+}
+
+#[test]
+fn prepare_changelog_skips_unchanged_crates() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let root = manifest.parent().unwrap();
+    let args = PrepareArgs {
+        changelog: Some(true),
+        changelog_notes: Some([("build1".to_owned(), "* a fix\n".to_owned())].into()),
+        skip_unchanged: Some(true),
+        ..Default::default()
+    };
+
+    let diff = prepare_diff(Some(&manifest), "2.0.0", &args).expect("prepare_diff failed");
+
+    assert!(diff.contains("--- /dev/null\n+++ b/build1/CHANGELOG.md\n"));
+    assert!(!diff.contains("dep1/CHANGELOG.md"));
+    assert!(!root.join("build1/CHANGELOG.md").exists());
+}
+
+#[test]
+fn prepare_commits_only_the_changed_files() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let root = manifest.parent().unwrap();
+    for args in [
+        &["init", "--quiet"][..],
+        &["config", "user.name", "Test"],
+        &["config", "user.email", "test@example.com"],
+        &["config", "commit.gpgsign", "false"],
+        &["add", "."],
+        &["commit", "--quiet", "-m", "initial"],
+    ] {
+        git(root, args);
+    }
+    fs::write(root.join("src/lib.rs"), "// unrelated change\n").expect("Couldn't write file");
+    let args = PrepareArgs {
+        changelog: Some(true),
+        changelog_date: Some("2020-02-02".into()),
+        commit: Some(true),
+        commit_message: Some("release {version}".into()),
+        ..Default::default()
+    };
+
+    prepare_with_args(Some(&manifest), "2.0.0".into(), &args).expect("prepare failed");
+
+    assert_eq!(git(root, &["log", "-1", "--format=%s"]), "release 2.0.0");
+    assert_eq!(git(root, &["status", "--porcelain"]), " M src/lib.rs");
+    let committed = git(root, &["show", "--name-only", "--format=", "HEAD"]);
+    let mut committed: Vec<_> = committed.lines().collect();
+    committed.sort_unstable();
+    assert_eq!(
+        committed,
+        vec![
+            "CHANGELOG.md",
+            "Cargo.lock",
+            "Cargo.toml",
+            "build1/CHANGELOG.md",
+            "build1/Cargo.toml",
+            "dep1/CHANGELOG.md",
+            "dep1/Cargo.toml",
+        ]
+    );
+}
+
 #[test]
 fn prepare_with_depedencies_from_alternate_registry() {
     with_env_var(
@@ -130,6 +283,104 @@ fn prepare_with_target_dependency() {
     assert_eq!(get_dep_version(cfg_unix, "dependencies", "dep1"), "2.0.0");
 }
 
+#[test]
+fn prepare_diff_shows_changes_without_writing() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let before = read_workspace_files(tempdir.path());
+
+    let diff = prepare_diff(Some(&manifest), "2.0.0", &PrepareArgs::default())
+        .expect("prepare_diff failed");
+
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+    assert!(diff.contains("--- a/Cargo.toml\n+++ b/Cargo.toml\n"));
+    assert!(diff.contains("--- a/dep1/Cargo.toml\n+++ b/dep1/Cargo.toml\n"));
+    assert!(diff.contains("+version = \"2.0.0\""));
+}
+
+#[test]
+fn prepare_diff_of_prepared_workspace_is_empty() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    prepare(Some(&manifest), "2.0.0".into()).expect("prepare failed");
+
+    let diff = prepare_diff(Some(&manifest), "2.0.0", &PrepareArgs::default())
+        .expect("prepare_diff failed");
+
+    assert_eq!(diff, "");
+}
+
+#[test]
+fn prepare_failure_in_a_member_leaves_workspace_unchanged() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let workspace_dir = manifest.parent().expect("no workspace dir");
+    // A member-level lockfile that doesn't describe the member itself can't be
+    // updated, so prepare fails part way through the workspace.
+    fs::write(
+        workspace_dir.join("dep1").join("Cargo.lock"),
+        "version = 3\n\n[[package]]\nname = \"other\"\nversion = \"0.1.0\"\n",
+    )
+    .expect("Couldn't write member lockfile");
+    let before = read_workspace_files(tempdir.path());
+
+    let result = prepare(Some(&manifest), "2.0.0".into());
+
+    assert!(result.is_err());
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+}
+
+#[test]
+fn prepare_failed_write_restores_files_already_written() {
+    let (tempdir, manifest) = copy_workspace("dependencies");
+    let workspace_dir = manifest.parent().expect("no workspace dir");
+    // The members are written in the order of their paths, so the root manifest and
+    // lockfile and build1 are written before dep1. A directory in the way of the
+    // temporary file for dep1 makes its write fail, even for root.
+    fs::create_dir(
+        workspace_dir
+            .join("dep1")
+            .join(".Cargo.toml.semantic-release-cargo.tmp"),
+    )
+    .expect("Couldn't create blocking dir");
+    let before = read_workspace_files(tempdir.path());
+
+    let result = prepare(Some(&manifest), "2.0.0".into());
+
+    assert_matches!(result, Err(Error::FileWriteError { .. }));
+    assert_eq!(read_workspace_files(tempdir.path()), before);
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .expect("Couldn't run git");
+    assert!(output.status.success(), "git {:?} failed", args);
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_owned()
+}
+
+fn read_workspace_files(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).expect("Couldn't read dir") {
+            let path = entry.expect("Couldn't read dir entry").path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                let contents = fs::read_to_string(&path).expect("Couldn't read file");
+                files.push((path, contents));
+            }
+        }
+    }
+    files.sort();
+
+    files
+}
+
 fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
     let workspace = workspace.as_ref();
     let tempdir = tempdir().expect("Couldn't create temp dir");
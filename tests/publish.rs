@@ -0,0 +1,261 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{
+    CargoInvocation, CargoRunner, Error, OutputStream, PublishArgs, PublishStatus,
+    RecordingCargoRunner, Workspace,
+};
+
+#[test]
+fn interrupted_publish_resumes_from_journal() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let journal = journal_path(&manifest);
+    let failing = Arc::new(RecordingCargoRunner::new().fail_for("dependencies"));
+    let workspace =
+        Workspace::with_runner(Some(&manifest), failing.clone()).expect("workspace failed");
+
+    let result = workspace.publish(&PublishArgs::default());
+
+    assert!(result.is_err());
+    let recorded = fs::read_to_string(&journal).expect("no journal after failed publish");
+    assert!(recorded.contains("\"dep1\""));
+    assert!(recorded.contains("\"build1\""));
+    assert_eq!(failing.invocations().len(), 3);
+
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+    let args = PublishArgs {
+        resume: Some(true),
+        ..Default::default()
+    };
+    let report = workspace.publish(&args).expect("resume failed");
+
+    let invocations = runner.invocations();
+    assert_eq!(invocations.len(), 1);
+    assert_eq!(invocations[0].package.as_deref(), Some("dependencies"));
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| c.status == PublishStatus::Published));
+    assert!(!journal.exists());
+}
+
+#[test]
+fn resume_with_changed_version_is_error() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let journal = journal_path(&manifest);
+    fs::create_dir_all(journal.parent().unwrap()).expect("Couldn't create journal dir");
+    fs::write(
+        &journal,
+        r#"{"registry":"crates.io","published":[{"name":"dep1","version":"9.9.9"}]}"#,
+    )
+    .expect("Couldn't write journal");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+    let args = PublishArgs {
+        resume: Some(true),
+        ..Default::default()
+    };
+
+    let result = workspace.publish(&args);
+
+    let err = result.expect_err("resume with a changed version succeeded");
+    assert_matches!(err, Error::JournalError { .. });
+    assert!(runner.invocations().is_empty());
+    assert!(journal.exists());
+}
+
+#[test]
+fn new_publish_with_journal_of_earlier_publish_is_error() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let journal = journal_path(&manifest);
+    fs::create_dir_all(journal.parent().unwrap()).expect("Couldn't create journal dir");
+    fs::write(
+        &journal,
+        r#"{"registry":"crates.io","published":[{"name":"dep1","version":"1.0.0"}]}"#,
+    )
+    .expect("Couldn't write journal");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let result = workspace.publish(&PublishArgs::default());
+
+    let err = result.expect_err("publish over the journal of an earlier publish succeeded");
+    assert_matches!(err, Error::JournalError { .. });
+    assert!(err.to_string().contains("--resume"));
+    assert!(runner.invocations().is_empty());
+    assert!(journal.exists());
+}
+
+#[test]
+fn publish_that_cannot_be_journaled_is_error() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let journal = journal_path(&manifest);
+    let runner = Arc::new(BlockJournalRunner {
+        journal_dir: journal.parent().unwrap().to_owned(),
+        recording: RecordingCargoRunner::new(),
+    });
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let result = workspace.publish(&PublishArgs::default());
+
+    assert_matches!(
+        result,
+        Err(Error::PublishFailed { inner, .. }) if matches!(*inner, Error::FileWriteError { .. })
+    );
+    assert_eq!(runner.recording.invocations().len(), 1);
+}
+
+#[test]
+fn publish_tags_published_crates() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let root = manifest.parent().unwrap();
+    for args in [
+        &["init", "--quiet"][..],
+        &["add", "."],
+        &[
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "initial",
+        ],
+    ] {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .status()
+            .expect("Couldn't run git");
+        assert!(status.success(), "git {:?} failed", args);
+    }
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace = Workspace::with_runner(Some(&manifest), runner).expect("workspace failed");
+    let args = PublishArgs {
+        packages: Some(vec!["dep1".into()]),
+        tag: Some(true),
+        ..Default::default()
+    };
+
+    std::env::set_var("GIT_COMMITTER_NAME", "Test");
+    std::env::set_var("GIT_COMMITTER_EMAIL", "test@example.com");
+    let result = workspace.publish(&args);
+    std::env::remove_var("GIT_COMMITTER_NAME");
+    std::env::remove_var("GIT_COMMITTER_EMAIL");
+
+    result.expect("publish failed");
+    let tags = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["tag", "--list"])
+        .output()
+        .expect("Couldn't run git");
+    assert_eq!(String::from_utf8_lossy(&tags.stdout), "dep1-v0.1.0\n");
+}
+
+#[test]
+fn publish_that_cannot_be_tagged_keeps_the_report() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace = Workspace::with_runner(Some(&manifest), runner).expect("workspace failed");
+    let args = PublishArgs {
+        tag: Some(true),
+        ..Default::default()
+    };
+
+    let result = workspace.publish(&args);
+
+    let Err(Error::PublishFailed { report, inner, .. }) = result else {
+        panic!(
+            "publish outside a git repository did not fail: {:?}",
+            result
+        );
+    };
+    assert_matches!(*inner, Error::GitError { .. } | Error::GitStatus { .. });
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| c.status == PublishStatus::Published));
+}
+
+/// A runner that puts a file where the journal directory belongs when a crate is
+/// published, so that the crate cannot be recorded in the journal.
+#[derive(Debug)]
+struct BlockJournalRunner {
+    journal_dir: PathBuf,
+    recording: RecordingCargoRunner,
+}
+
+impl CargoRunner for BlockJournalRunner {
+    fn run(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.recording.run(invocation)
+    }
+
+    fn publish(
+        &self,
+        invocation: &CargoInvocation,
+        on_line: &mut dyn FnMut(OutputStream, &str),
+    ) -> io::Result<Output> {
+        fs::create_dir_all(self.journal_dir.parent().unwrap())?;
+        fs::write(&self.journal_dir, "")?;
+        self.recording.publish(invocation, on_line)
+    }
+
+    fn metadata(&self, invocation: &CargoInvocation) -> io::Result<Output> {
+        self.recording.metadata(invocation)
+    }
+}
+
+fn journal_path(manifest: &Path) -> PathBuf {
+    manifest
+        .parent()
+        .unwrap()
+        .join("target")
+        .join("semantic-release-cargo")
+        .join("journal.json")
+}
+
+fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let mut cargo_toml = tempdir.path().join(workspace);
+    cargo_toml.push("Cargo.toml");
+
+    (tempdir, cargo_toml)
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
@@ -0,0 +1,143 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use assert_matches::assert_matches;
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{
+    CargoInvocation, CargoRunner, Error, OutputStream, ProcessCargoRunner, PublishArgs, Workspace,
+};
+
+#[test]
+fn publish_passes_each_line_of_output() {
+    let runner = ProcessCargoRunner::with_program("sh");
+    let invocation = CargoInvocation {
+        args: vec![
+            "-c".to_owned(),
+            "echo Packaging >&2; echo done; echo Uploading >&2".to_owned(),
+        ],
+        ..Default::default()
+    };
+    let mut lines = Vec::new();
+
+    let output = runner
+        .publish(&invocation, &mut |stream, line| {
+            lines.push((stream, line.to_owned()))
+        })
+        .expect("publish failed");
+
+    assert!(output.status.success());
+    assert_eq!(output.stderr, b"Packaging\nUploading\n");
+    assert_eq!(output.stdout, b"done\n");
+    let stderr: Vec<_> = lines
+        .iter()
+        .filter(|(stream, _)| *stream == OutputStream::Stderr)
+        .map(|(_, line)| line.as_str())
+        .collect();
+    assert_eq!(stderr, ["Packaging", "Uploading"]);
+}
+
+#[test]
+fn publish_past_timeout_is_killed() {
+    let runner = ProcessCargoRunner::with_program("sh");
+    let invocation = CargoInvocation {
+        args: vec![
+            "-c".to_owned(),
+            "echo Verifying >&2; exec sleep 30".to_owned(),
+        ],
+        timeout: Some(Duration::from_millis(500)),
+        ..Default::default()
+    };
+    let mut lines = Vec::new();
+    let start = Instant::now();
+
+    let result = runner.publish(&invocation, &mut |_, line| lines.push(line.to_owned()));
+
+    assert_matches!(result, Err(err) if err.kind() == io::ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(lines, ["Verifying"]);
+}
+
+#[test]
+fn workspace_publish_past_timeout_is_timeout_error() {
+    let (tempdir, manifest) = copy_workspace("basic");
+    let script = slow_publish_cargo(tempdir.path());
+    let runner = Arc::new(ProcessCargoRunner::with_program(script));
+    let workspace = Workspace::with_runner(Some(&manifest), runner).expect("workspace failed");
+    let args = PublishArgs {
+        timeout: Some(1),
+        ..Default::default()
+    };
+
+    let result = workspace.publish(&args);
+
+    let Err(Error::PublishFailed { inner, .. }) = result else {
+        panic!("publish past the timeout did not fail: {:?}", result);
+    };
+    assert_matches!(
+        *inner,
+        Error::CargoPublishTimeout { timeout, ref manifest_path, .. }
+            if timeout == Duration::from_secs(1) && *manifest_path == manifest
+    );
+}
+
+/// Write a `cargo` that hangs in `cargo publish` and hands everything else to the
+/// real cargo.
+fn slow_publish_cargo(dir: &Path) -> PathBuf {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    let path = dir.join("slow-cargo");
+    let script = format!(
+        "#!/bin/sh\n\
+         if [ \"$1\" = publish ]; then\n\
+         \techo \"Packaging $3\" >&2\n\
+         \texec sleep 30\n\
+         fi\n\
+         exec \"{}\" \"$@\"\n",
+        cargo
+    );
+
+    fs::write(&path, script).expect("Couldn't write the fake cargo");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+        .expect("Couldn't make the fake cargo executable");
+
+    path
+}
+
+fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let mut cargo_toml = tempdir.path().join(workspace);
+    cargo_toml.push("Cargo.toml");
+
+    (tempdir, cargo_toml)
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
@@ -0,0 +1,199 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{
+    CargoInvocation, CargoRunner, Error, PublishArgs, PublishStatus, RecordingCargoRunner,
+    Workspace, YankArgs, YankStatus,
+};
+
+#[test]
+fn publish_runs_cargo_publish_for_each_crate() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let report = workspace
+        .publish(&PublishArgs::default())
+        .expect("publish failed");
+
+    let invocations = runner.invocations();
+    assert_eq!(packages(&invocations), ["dep1", "build1", "dependencies"]);
+    let root = manifest.parent().unwrap();
+    for invocation in &invocations {
+        let name = invocation.package.as_deref().unwrap();
+        let crate_manifest = match name {
+            "dependencies" => manifest.clone(),
+            _ => root.join(name).join("Cargo.toml"),
+        };
+        assert_eq!(
+            invocation.args,
+            [
+                "publish",
+                "--manifest-path",
+                crate_manifest.to_str().unwrap(),
+                "--allow-dirty"
+            ]
+        );
+    }
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| c.status == PublishStatus::Published));
+}
+
+#[test]
+fn publish_with_failing_crate_is_error() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new().fail_for("build1"));
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let result = workspace.publish(&PublishArgs::default());
+
+    let Err(Error::PublishFailed { report, inner, .. }) = result else {
+        panic!("publish with a failing crate did not fail: {:?}", result);
+    };
+    assert_matches!(*inner, Error::CargoPublishStatus { .. });
+    let statuses: Vec<_> = report
+        .crates
+        .iter()
+        .map(|c| (c.name.as_str(), c.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("dep1", PublishStatus::Published),
+            ("build1", PublishStatus::Failed),
+            ("dependencies", PublishStatus::Skipped)
+        ]
+    );
+    assert_eq!(packages(&runner.invocations()), ["dep1", "build1"]);
+}
+
+#[test]
+fn yank_runs_cargo_yank_in_reverse_order() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+    let args = YankArgs {
+        registry: Some("internal".to_owned()),
+        undo: Some(true),
+    };
+
+    let report = workspace.yank("2.0.0", &args).expect("yank failed");
+
+    let invocations = runner.invocations();
+    assert_eq!(packages(&invocations), ["dependencies", "build1", "dep1"]);
+    assert_eq!(
+        invocations[0].args,
+        [
+            "yank",
+            "--version",
+            "2.0.0",
+            "--undo",
+            "--registry",
+            "internal",
+            "dependencies"
+        ]
+    );
+    assert_eq!(invocations[0].current_dir.as_deref(), manifest.parent());
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| c.status == YankStatus::Unyanked));
+}
+
+#[test]
+fn package_is_recorded() {
+    let runner = RecordingCargoRunner::new().fail_for("dep2");
+    let invocation = |package: &str| CargoInvocation {
+        package: Some(package.to_owned()),
+        args: vec!["package".to_owned(), "-p".to_owned(), package.to_owned()],
+        ..Default::default()
+    };
+
+    let packaged = runner.package(&invocation("dep1")).expect("package failed");
+    let failed = runner.package(&invocation("dep2")).expect("package failed");
+
+    assert!(packaged.status.success());
+    assert!(!failed.status.success());
+    assert_eq!(
+        runner.invocations(),
+        [invocation("dep1"), invocation("dep2")]
+    );
+}
+
+#[test]
+fn workspace_from_metadata_json_publishes_with_runner() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let output = Command::new(env!("CARGO"))
+        .args(["metadata", "--format-version", "1", "--manifest-path"])
+        .arg(&manifest)
+        .output()
+        .expect("Couldn't run cargo metadata");
+    assert!(output.status.success());
+    let json = String::from_utf8(output.stdout).expect("metadata is not UTF-8");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::from_metadata_json_with_runner(&json, runner.clone()).expect("workspace failed");
+
+    let report = workspace
+        .publish(&PublishArgs::default())
+        .expect("publish failed");
+
+    let invocations = runner.invocations();
+    assert_eq!(packages(&invocations), ["dep1", "build1", "dependencies"]);
+    assert!(invocations
+        .iter()
+        .all(|invocation| invocation.args[0] == "publish"
+            && invocation.current_dir.as_deref() == manifest.parent()));
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| c.status == PublishStatus::Published));
+}
+
+fn packages(invocations: &[CargoInvocation]) -> Vec<&str> {
+    invocations
+        .iter()
+        .map(|invocation| invocation.package.as_deref().unwrap())
+        .collect()
+}
+
+fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let mut cargo_toml = tempdir.path().join(workspace);
+    cargo_toml.push("Cargo.toml");
+
+    (tempdir, cargo_toml)
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
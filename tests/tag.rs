@@ -0,0 +1,130 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use assert_matches::assert_matches;
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{tag, Error, TagArgs, TagStatus};
+
+#[test]
+fn tag_creates_annotated_tag_per_crate() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let root = manifest.parent().unwrap();
+
+    let report = tag(Some(&manifest), &TagArgs::default()).expect("tag failed");
+
+    let names: Vec<_> = report.tags.iter().map(|tag| tag.tag.as_str()).collect();
+    assert_eq!(names.len(), 3);
+    assert_eq!(names[2], "dependencies-v0.1.0");
+    assert!(names.contains(&"dep1-v0.1.0"));
+    assert!(report.tags.iter().all(|t| t.status == TagStatus::Created));
+    assert_eq!(git(root, &["cat-file", "-t", "dep1-v0.1.0"]), "tag");
+    assert_eq!(
+        git(root, &["rev-parse", "dep1-v0.1.0^{commit}"]),
+        report.commit
+    );
+}
+
+#[test]
+fn tag_keeps_tags_at_head() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let args = TagArgs {
+        tag_format: Some("{name}@{version}".into()),
+        packages: Some(vec!["dep1".into()]),
+        ..Default::default()
+    };
+    tag(Some(&manifest), &args).expect("tag failed");
+
+    let report = tag(Some(&manifest), &args).expect("second tag failed");
+
+    assert_eq!(report.tags.len(), 1);
+    assert_eq!(report.tags[0].tag, "dep1@0.1.0");
+    assert_eq!(report.tags[0].status, TagStatus::Existing);
+}
+
+#[test]
+fn tag_at_another_commit_is_error() {
+    let (_tempdir, manifest) = copy_workspace_repo("dependencies");
+    let root = manifest.parent().unwrap();
+    git(root, &["tag", "build1-v0.1.0"]);
+    git(
+        root,
+        &["commit", "--quiet", "--allow-empty", "-m", "second"],
+    );
+
+    let result = tag(Some(&manifest), &TagArgs::default());
+
+    let err = result.expect_err("tag with a conflicting tag succeeded");
+    assert_matches!(
+        err,
+        Error::TagConflict { tag, .. } if tag == "build1-v0.1.0"
+    );
+    assert_eq!(git(root, &["tag", "--list"]), "build1-v0.1.0");
+}
+
+#[test]
+fn tag_format_without_version_is_error() {
+    let (_tempdir, manifest) = copy_workspace_repo("basic");
+    let args = TagArgs {
+        tag_format: Some("release-{name}".into()),
+        ..Default::default()
+    };
+
+    let result = tag(Some(&manifest), &args);
+
+    assert_matches!(
+        result.expect_err("tag with a bad format succeeded"),
+        Error::TagFormatError { .. }
+    );
+}
+
+/// Copy a test workspace into a new git repository with a single commit.
+fn copy_workspace_repo(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let root = tempdir.path().join(workspace);
+    git(&root, &["init", "--quiet"]);
+    git(&root, &["config", "user.name", "Test"]);
+    git(&root, &["config", "user.email", "test@example.com"]);
+    git(&root, &["config", "commit.gpgsign", "false"]);
+    git(&root, &["config", "tag.gpgsign", "false"]);
+    git(&root, &["add", "."]);
+    git(&root, &["commit", "--quiet", "-m", "initial"]);
+
+    (tempdir, root.join("Cargo.toml"))
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .expect("Couldn't run git");
+    assert!(output.status.success(), "git {:?} failed", args);
+
+    String::from_utf8_lossy(&output.stdout).trim().to_owned()
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
@@ -12,8 +12,10 @@ use std::path::{Path, PathBuf};
 
 use assert_matches::assert_matches;
 
-use semantic_release_cargo::{verify_conditions, verify_conditions_with_alternate};
-// use semantic_release_cargo::Error;
+use semantic_release_cargo::Error;
+use semantic_release_cargo::{
+    verify_conditions, verify_conditions_with_alternate, verify_conditions_with_args, VerifyArgs,
+};
 
 #[test]
 fn verify_simple_workspaces_is_ok() {
@@ -87,6 +89,98 @@ fn verify_with_git_dependency_is_error() {
     // );
 }
 
+#[test]
+fn verify_with_url_template_is_ok() {
+    set_registry_token();
+    let path = get_test_data_manifest_path("basic");
+    let args = VerifyArgs {
+        url_template: Some("https://crates.internal/crates/{name}/{version}".into()),
+        ..Default::default()
+    };
+
+    let result = verify_conditions_with_args(Some(&path), &args);
+
+    assert_matches!(result, Ok(_));
+}
+
+#[test]
+fn verify_with_invalid_url_template_is_error() {
+    set_registry_token();
+    let path = get_test_data_manifest_path("basic");
+    let args = VerifyArgs {
+        url_template: Some("crates.internal/crates/{name}/{version}".into()),
+        ..Default::default()
+    };
+
+    let result = verify_conditions_with_args(Some(&path), &args);
+
+    assert_matches!(result, Err(Error::UrlError(_)));
+}
+
+#[test]
+fn verify_with_invalid_url_template_in_metadata_is_error() {
+    set_registry_token();
+    let path = get_test_data_manifest_path("invalid_url_template");
+
+    let result = verify_conditions(Some(&path));
+
+    assert_matches!(result, Err(Error::UrlError(_)));
+}
+
+#[test]
+fn verify_with_main_crate_is_ok() {
+    set_registry_token();
+    with_env_var(
+        "CARGO_REGISTRIES_TEST_INDEX",
+        "https://github.com/rust-lang/crates.io-index",
+        || {
+            let path = get_test_data_manifest_path("dependencies");
+            let args = VerifyArgs {
+                main_crate: Some("dep1".into()),
+                ..Default::default()
+            };
+
+            let result = verify_conditions_with_args(Some(&path), &args);
+
+            assert_matches!(result, Ok(_));
+        },
+    );
+}
+
+#[test]
+fn verify_with_unknown_main_crate_is_error() {
+    set_registry_token();
+    let path = get_test_data_manifest_path("basic");
+    let args = VerifyArgs {
+        main_crate: Some("unknown".into()),
+        ..Default::default()
+    };
+
+    let result = verify_conditions_with_args(Some(&path), &args);
+
+    assert_matches!(result, Err(Error::VerifyError { .. }));
+}
+
+#[test]
+fn verify_with_main_crate_not_publishable_to_registry_is_error() {
+    set_registry_token();
+    with_env_var(
+        "CARGO_REGISTRIES_TEST_INDEX",
+        "https://github.com/rust-lang/crates.io-index",
+        || {
+            let path = get_test_data_manifest_path("dependencies_alternate_registry");
+            let args = VerifyArgs {
+                main_crate: Some("dependencies_alt_registry".into()),
+                ..Default::default()
+            };
+
+            let result = verify_conditions_with_args(Some(&path), &args);
+
+            assert_matches!(result, Err(Error::VerifyError { .. }));
+        },
+    );
+}
+
 #[ignore]
 #[test]
 fn verify_with_git_and_version_dependency_is_ok() {
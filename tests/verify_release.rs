@@ -0,0 +1,104 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{
+    verify_release, Error, RecordingCargoRunner, VerifyReleaseArgs, Workspace,
+};
+
+#[test]
+fn verify_release_checks_each_crate_with_the_registry() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let result = workspace.verify_release("2.0.0", &VerifyReleaseArgs::default());
+
+    result.expect("verify_release failed");
+    let invocations = runner.invocations();
+    assert_eq!(invocations.len(), 3);
+    assert!(invocations.iter().any(|invocation| invocation.args
+        == ["info", "--quiet", "dep1@2.0.0", "--registry", "crates-io"]));
+}
+
+#[test]
+fn verify_release_with_published_version_is_error() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new().published("dep1", "2.0.0"));
+    let workspace = Workspace::with_runner(Some(&manifest), runner).expect("workspace failed");
+
+    let result = workspace.verify_release("2.0.0", &VerifyReleaseArgs::default());
+
+    let err = result.expect_err("verify_release of a published version succeeded");
+    assert_matches!(
+        err,
+        Error::VerifyError { reason, .. } if reason.contains("dep1")
+    );
+}
+
+#[test]
+fn verify_release_with_earlier_version_is_error() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+    let args = VerifyReleaseArgs {
+        check_registry: Some(false),
+        ..Default::default()
+    };
+
+    let result = workspace.verify_release("0.0.9", &args);
+
+    assert_matches!(
+        result.expect_err("verify_release of an earlier version succeeded"),
+        Error::VerifyError { .. }
+    );
+    assert!(runner.invocations().is_empty());
+}
+
+#[test]
+fn verify_release_with_invalid_version_is_error() {
+    let (_tempdir, manifest) = copy_workspace("basic");
+
+    let result = verify_release(Some(&manifest), "v2", &VerifyReleaseArgs::default());
+
+    assert_matches!(
+        result.expect_err("verify_release of an invalid version succeeded"),
+        Error::VerifyError { .. }
+    );
+}
+
+fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let mut cargo_toml = tempdir.path().join(workspace);
+    cargo_toml.push("Cargo.toml");
+
+    (tempdir, cargo_toml)
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
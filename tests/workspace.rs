@@ -0,0 +1,134 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use assert_matches::assert_matches;
+use fs_extra::dir::{copy, CopyOptions};
+use tempfile::{tempdir, TempDir};
+
+use semantic_release_cargo::{
+    Error, ListPackagesArgs, PrepareArgs, PublishArgs, VerifyArgs, Workspace,
+};
+
+#[test]
+fn workspace_lists_packages_in_publish_order() {
+    let path = get_test_data_manifest_path("dependencies");
+
+    let workspace = Workspace::new(Some(&path)).expect("unable to load workspace");
+    let packages = workspace.packages(&ListPackagesArgs::default());
+
+    assert_eq!(
+        workspace.root(),
+        path.parent().unwrap().canonicalize().unwrap()
+    );
+    assert_eq!(packages.len(), 3);
+    assert_eq!(packages[2].name, "dependencies");
+}
+
+#[test]
+fn workspace_verifies_conditions() {
+    env::set_var("CARGO_REGISTRY_TOKEN", "fake_token");
+    let path = get_test_data_manifest_path("dependencies");
+
+    let workspace = Workspace::new(Some(&path)).expect("unable to load workspace");
+
+    workspace
+        .verify(&VerifyArgs::default())
+        .expect("verify failed");
+}
+
+#[test]
+fn workspace_sees_prepared_versions() {
+    let (_tempdir, manifest) = copy_workspace("dependencies");
+    let mut workspace = Workspace::new(Some(&manifest)).expect("unable to load workspace");
+
+    workspace
+        .prepare("2.0.0", &PrepareArgs::default())
+        .expect("prepare failed");
+
+    let packages = workspace.packages(&ListPackagesArgs::default());
+    assert!(packages.iter().all(|pkg| pkg.version == "2.0.0"));
+}
+
+#[test]
+fn workspace_plans_prepared_changes() {
+    let path = get_test_data_manifest_path("dependencies");
+    let workspace = Workspace::new(Some(&path)).expect("unable to load workspace");
+
+    let plan = workspace
+        .plan("2.0.0", &PrepareArgs::default(), &PublishArgs::default())
+        .expect("plan failed");
+    let diff = workspace
+        .prepare_diff("2.0.0", &PrepareArgs::default())
+        .expect("prepare_diff failed");
+
+    assert_eq!(plan.diff, diff);
+    assert_eq!(plan.publish_order.len(), 2);
+}
+
+#[test]
+fn workspace_from_metadata_json() {
+    let path = get_test_data_manifest_path("dependencies");
+    let output = Command::new(env!("CARGO"))
+        .args(["metadata", "--format-version", "1", "--manifest-path"])
+        .arg(&path)
+        .output()
+        .expect("Couldn't run cargo metadata");
+    assert!(output.status.success());
+    let json = String::from_utf8(output.stdout).expect("metadata is not UTF-8");
+
+    let workspace = Workspace::from_metadata_json(&json).expect("unable to load workspace");
+    let packages = workspace.packages(&ListPackagesArgs::default());
+
+    assert_eq!(packages.len(), 3);
+    assert_eq!(packages[2].name, "dependencies");
+    assert_eq!(packages[2].dependencies.len(), 2);
+}
+
+#[test]
+fn workspace_from_invalid_metadata_json_is_error() {
+    let result = Workspace::from_metadata_json("{}");
+
+    assert_matches!(
+        result.expect_err("invalid metadata was accepted"),
+        Error::WorkspaceError(_)
+    );
+}
+
+fn copy_workspace(workspace: impl AsRef<Path>) -> (TempDir, PathBuf) {
+    let workspace = workspace.as_ref();
+    let tempdir = tempdir().expect("Couldn't create temp dir");
+    let srcdir = get_workspace_dir(workspace);
+
+    copy(srcdir, tempdir.path(), &CopyOptions::new()).expect("Couldn't copy the workspace");
+    let mut cargo_toml = tempdir.path().join(workspace);
+    cargo_toml.push("Cargo.toml");
+
+    (tempdir, cargo_toml)
+}
+
+fn get_test_data_manifest_path(dir: impl AsRef<Path>) -> PathBuf {
+    let mut path = get_workspace_dir(dir);
+    path.push("Cargo.toml");
+
+    path
+}
+
+fn get_workspace_dir(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+
+    path
+}
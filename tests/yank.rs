@@ -0,0 +1,126 @@
+// Copyright 2020 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+
+use semantic_release_cargo::{
+    CargoInvocation, Error, RecordingCargoRunner, Workspace, YankArgs, YankStatus,
+};
+
+#[test]
+fn yank_runs_in_reverse_publish_order() {
+    let manifest = get_test_data_manifest_path("dependencies");
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let report = workspace
+        .yank("2.0.0", &YankArgs::default())
+        .expect("yank failed");
+
+    let invocations = runner.invocations();
+    assert_eq!(invocations.len(), 3);
+    assert_eq!(
+        invocations[0].args,
+        ["yank", "--version", "2.0.0", "dependencies"]
+    );
+    assert_eq!(report.crates[0].name, "dependencies");
+    assert!(report.crates.iter().all(|c| c.status == YankStatus::Yanked));
+    assert!(!report.undo);
+}
+
+#[test]
+fn yank_undo_with_registry() {
+    let manifest = get_test_data_manifest_path("dependencies_alternate_registry");
+    let args = YankArgs {
+        registry: Some("test".into()),
+        undo: Some(true),
+    };
+
+    std::env::set_var(
+        "CARGO_REGISTRIES_TEST_INDEX",
+        "https://github.com/rust-lang/crates.io-index",
+    );
+    let runner = Arc::new(RecordingCargoRunner::new());
+    let workspace = Workspace::with_runner(Some(&manifest), runner.clone());
+    std::env::remove_var("CARGO_REGISTRIES_TEST_INDEX");
+
+    let report = workspace
+        .expect("workspace failed")
+        .yank("2.0.0", &args)
+        .expect("yank failed");
+
+    assert!(!report.crates.is_empty());
+    assert_eq!(report.registry, "test");
+    assert!(report.undo);
+    assert!(runner
+        .invocations()
+        .iter()
+        .all(|invocation| invocation.args[..6]
+            == ["yank", "--version", "2.0.0", "--undo", "--registry", "test"]));
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| c.status == YankStatus::Unyanked));
+}
+
+#[test]
+fn yank_skips_unpublished_crates_and_reports_failures() {
+    let manifest = get_test_data_manifest_path("dependencies");
+    let runner = Arc::new(
+        RecordingCargoRunner::new()
+            .fail_with(
+                "dependencies",
+                "error: crate `dependencies` does not have a version `2.0.0`",
+            )
+            .fail_with("dep1", "error: failed to yank"),
+    );
+    let workspace =
+        Workspace::with_runner(Some(&manifest), runner.clone()).expect("workspace failed");
+
+    let result = workspace.yank("2.0.0", &YankArgs::default());
+
+    let err = result.expect_err("yank with a failure succeeded");
+    let report = err.yank_report().expect("no report of the failed yank");
+    assert_eq!(report.crates[0].status, YankStatus::Unpublished);
+    assert!(report
+        .crates
+        .iter()
+        .all(|c| (c.status == YankStatus::Failed) == (c.name == "dep1")));
+    assert_matches!(
+        err,
+        Error::YankFailed { inner, .. }
+            if matches!(*inner, Error::CargoYankStatus { ref name, .. } if name == "dep1")
+    );
+    assert_eq!(
+        packages(&runner.invocations()),
+        ["dependencies", "build1", "dep1"]
+    );
+}
+
+fn packages(invocations: &[CargoInvocation]) -> Vec<&str> {
+    invocations
+        .iter()
+        .map(|invocation| invocation.package.as_deref().unwrap())
+        .collect()
+}
+
+fn get_test_data_manifest_path(workspace: impl AsRef<Path>) -> PathBuf {
+    let mut path = PathBuf::from(file!());
+
+    path.pop();
+    path.pop();
+    path.push("test_data");
+    path.push(workspace);
+    path.push("Cargo.toml");
+
+    path
+}